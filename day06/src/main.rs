@@ -1,26 +1,59 @@
 use std::collections::HashMap;
+use std::fs;
 
 const INPUT: &'static [u32] = &[4, 10, 4, 1, 8, 4, 9, 14, 5, 1, 14, 15, 0, 15, 3, 5];
+const HEATMAP_CHARS: &[u8] = b" .:-=+*#%@";
 
 fn main() {
-    let mut banks = Vec::from(INPUT);
+    // pass --history to export the states to history.csv and print a heatmap
+    let record_history = std::env::args().any(|arg| arg == "--history");
+    let result = find_cycle(Vec::from(INPUT), record_history);
+
+    println!("Part 1: steps={}", result.steps);
+    println!("Part 2: steps={}", result.cycle_len());
+
+    if let Some(history) = &result.history {
+        fs::write("history.csv", history_to_csv(history)).unwrap();
+        println!("History saved to history.csv");
+        print!("{}", render_heatmap(history, result.cycle_start, result.steps));
+    }
+}
+
+struct CycleResult {
+    steps: usize,
+    cycle_start: usize,
+    history: Option<Vec<Vec<u32>>>,
+}
+
+impl CycleResult {
+    fn cycle_len(&self) -> usize {
+        self.steps - self.cycle_start
+    }
+}
+
+/// Redistribute until a state repeats. If requested, history contains all the
+/// states from the initial one up to and including the first repeated one.
+fn find_cycle(mut banks: Vec<u32>, record_history: bool) -> CycleResult {
     let mut seen_states = HashMap::new();
     seen_states.insert(banks.clone(), 0);
+    let mut history = if record_history { Some(vec![banks.clone()]) } else { None };
 
     let mut steps = 0;
-    let prev_steps: i32;
+    let cycle_start: usize;
 
     loop {
         banks = redistribute_banks(banks);
         steps += 1;
+        if let Some(h) = &mut history {
+            h.push(banks.clone());
+        }
         if let Some(v) = seen_states.insert(banks.clone(), steps) {
-            prev_steps = v;
+            cycle_start = v;
             break
         }
     }
 
-    println!("Part 1: steps={}", steps);
-    println!("Part 2: steps={}", steps - prev_steps);
+    CycleResult { steps, cycle_start, history }
 }
 
 fn redistribute_banks(mut banks: Vec<u32>) -> Vec<u32> {
@@ -48,6 +81,48 @@ fn redistribute_banks(mut banks: Vec<u32>) -> Vec<u32> {
     banks
 }
 
+fn history_to_csv(history: &[Vec<u32>]) -> String {
+    let banks_count = history.first().map_or(0, |banks| banks.len());
+    let mut csv = String::from("step");
+    for i in 0..banks_count {
+        csv += &format!(",bank{}", i);
+    }
+    csv.push('\n');
+
+    for (step, banks) in history.iter().enumerate() {
+        csv += &step.to_string();
+        for v in banks {
+            csv += &format!(",{}", v);
+        }
+        csv.push('\n');
+    }
+
+    csv
+}
+
+/// One row per step and one column per bank, darker chars meaning fuller banks.
+/// Rows where the cycle starts and ends are marked with '>' and '<'.
+fn render_heatmap(history: &[Vec<u32>], cycle_start: usize, cycle_end: usize) -> String {
+    let max = history.iter().flatten().copied().max().unwrap_or(0).max(1);
+    let levels = HEATMAP_CHARS.len() as u32 - 1;
+    let step_width = history.len().saturating_sub(1).to_string().len();
+    let mut map = String::new();
+
+    for (step, banks) in history.iter().enumerate() {
+        let mark = match step {
+            s if s == cycle_start => '>',
+            s if s == cycle_end => '<',
+            _ => ' ',
+        };
+        let cells: String = banks.iter()
+            .map(|v| HEATMAP_CHARS[(v * levels).div_ceil(max) as usize] as char)
+            .collect();
+        map += &format!("{:>width$} {}|{}|\n", step, mark, cells, width = step_width);
+    }
+
+    map
+}
+
 
 #[cfg(test)]
 mod test {
@@ -67,4 +142,24 @@ mod test {
         assert_eq!(result, expects, "Redistribution of bank {:?} expected {:?}, got {:?}",
             input, expects, result);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn cycle_history() {
+        let result = find_cycle(vec![0, 2, 7, 0], true);
+        assert_eq!((result.steps, result.cycle_len()), (5, 4));
+
+        let history = result.history.unwrap();
+        assert_eq!(history.len(), 6);
+        assert_eq!(history[result.cycle_start], history[result.steps]);
+        assert_eq!(history[5], vec![2, 4, 1, 2]);
+
+        assert!(find_cycle(vec![0, 2, 7, 0], false).history.is_none());
+    }
+
+    #[test]
+    fn history_export() {
+        let history = vec![vec![0, 2, 7, 0], vec![2, 4, 1, 2]];
+        assert_eq!(history_to_csv(&history), "step,bank0,bank1,bank2,bank3\n0,0,2,7,0\n1,2,4,1,2\n");
+        assert_eq!(render_heatmap(&history, 0, 1), "0 >| -@ |\n1 <|-*:-|\n");
+    }
+}
@@ -0,0 +1,224 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use super::Prog;
+
+/// Change of own weight of a prog, signed: negative means it's too heavy
+pub struct Fix<'a> {
+    pub prog: &'a Prog,
+    pub delta: i64,
+}
+
+impl Fix<'_> {
+    pub fn new_own_weight(&self) -> i64 {
+        self.prog.own_weight as i64 + self.delta
+    }
+}
+
+/// Set of fixes that would make all the progs above weigh target_weight
+pub struct Candidate<'a> {
+    pub target_weight: u32,
+    pub fixes: Vec<Fix<'a>>,
+}
+
+/// Prog whose progs above don't weigh the same. If there isn't a weight shared
+/// by more progs than any other, it's ambiguous which ones are wrong and the
+/// candidates for each of the most common weights are listed. Candidates that
+/// would need a negative own weight are left out, so there may be none.
+/// If the only wrong prog is unbalanced itself, the fault is caused by it and
+/// goes away once the faults above are fixed.
+pub struct Fault<'a> {
+    pub prog: &'a Prog,
+    pub candidates: Vec<Candidate<'a>>,
+    pub caused_by: Option<&'a Prog>,
+}
+
+impl Fault<'_> {
+    pub fn is_ambiguous(&self) -> bool {
+        self.candidates.len() != 1
+    }
+}
+
+impl fmt::Display for Fault<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.prog.name)?;
+        if let Some(cause) = self.caused_by {
+            return write!(f, " (caused by \"{}\")", cause.name);
+        }
+        if self.candidates.is_empty() {
+            write!(f, " (no valid fix)")?;
+        } else if self.is_ambiguous() {
+            write!(f, " (ambiguous)")?;
+        }
+        for candidate in &self.candidates {
+            write!(f, "\n   weight {}:", candidate.target_weight)?;
+            for fix in &candidate.fixes {
+                write!(f, " set \"{}\".own_weight={} ({:+})", fix.prog.name, fix.new_own_weight(), fix.delta)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn check(prog: &Prog) -> Option<Fault<'_>> {
+    if !is_unbalanced(prog) {
+        return None;
+    }
+
+    let weights: Vec<(u32, u32)> = prog.progs_above.iter().map(|p| (p.weight(), p.own_weight)).collect();
+    let candidates: Vec<Candidate> = fixes(&weights).into_iter()
+        .map(|(target_weight, fixes)| Candidate {
+            target_weight,
+            fixes: fixes.into_iter().map(|(i, delta)| Fix { prog: &prog.progs_above[i], delta }).collect(),
+        })
+        .collect();
+    let caused_by = match candidates.as_slice() {
+        [candidate] if candidate.fixes.len() == 1 && is_unbalanced(candidate.fixes[0].prog) =>
+            Some(candidate.fixes[0].prog),
+        _ => None,
+    };
+
+    Some(Fault { prog, candidates, caused_by })
+}
+
+fn is_unbalanced(prog: &Prog) -> bool {
+    prog.progs_above.windows(2).any(|p| p[0].weight() != p[1].weight())
+}
+
+/// Ways to balance progs with the given (total weight, own weight): for each of
+/// the most common total weights, the (index, delta) of the progs to change.
+/// The ways that would need a negative own weight are left out.
+pub fn fixes(weights: &[(u32, u32)]) -> Vec<(u32, Vec<(usize, i64)>)> {
+    let mut counts: BTreeMap<u32, usize> = BTreeMap::new();
+    for &(total, _) in weights {
+        *counts.entry(total).or_default() += 1;
+    }
+    let max_count = counts.values().copied().max().unwrap_or(0);
+
+    counts.iter()
+        .filter(|&(_, &count)| count == max_count)
+        .map(|(&target_weight, _)| {
+            let fixes: Vec<_> = weights.iter().enumerate()
+                .filter(|(_, &(total, _))| total != target_weight)
+                .map(|(i, &(total, _))| (i, target_weight as i64 - total as i64))
+                .collect();
+            (target_weight, fixes)
+        })
+        .filter(|(_, fixes)| fixes.iter().all(|&(i, delta)| weights[i].1 as i64 + delta >= 0))
+        .collect()
+}
+
+/// Index of the only prog that doesn't weigh like the others and the change of
+/// own weight it needs, if there's exactly one and it's not ambiguous which
+pub fn odd_one_out(weights: &[(u32, u32)]) -> Option<(usize, i64)> {
+    match fixes(weights).as_slice() {
+        [(_, fixes)] if fixes.len() == 1 => Some(fixes[0]),
+        _ => None,
+    }
+}
+
+/// All the unbalanced progs of the tower, from the bottom up
+pub fn report(root: &Prog) -> Vec<Fault<'_>> {
+    let mut faults = Vec::new();
+    let mut pending = vec![root];
+
+    while let Some(prog) = pending.pop() {
+        faults.extend(check(prog));
+        pending.extend(prog.progs_above.iter().rev());
+    }
+
+    faults
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::{build_graph, find_unbalanced, parse_progs};
+
+    fn build(input: &str) -> Prog {
+        build_graph(&parse_progs(input.as_bytes())).unwrap()
+    }
+
+    fn fixes<'a>(candidate: &Candidate<'a>) -> Vec<(&'a str, i64)> {
+        candidate.fixes.iter().map(|f| (f.prog.name.as_str(), f.delta)).collect()
+    }
+
+    #[test]
+    fn single_fault() {
+        let tower = build("a (1) -> b, c, d\nb (5)\nc (5)\nd (2)");
+        let faults = report(&tower);
+        assert_eq!(faults.len(), 1);
+        assert!(!faults[0].is_ambiguous());
+        assert_eq!(faults[0].candidates[0].target_weight, 5);
+        assert_eq!(fixes(&faults[0].candidates[0]), vec![("d", 3)]);
+        assert_eq!(faults[0].candidates[0].fixes[0].new_own_weight(), 5);
+    }
+
+    #[test]
+    fn multiple_faults() {
+        let tower = build("a (1) -> b, c, d\nb (8)\nc (5) -> e, f, g\nd (8)\ne (1)\nf (1)\ng (2)");
+        let faults = report(&tower);
+        let names: Vec<&str> = faults.iter().map(|f| f.prog.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "c"]);
+        assert!(faults.iter().all(|f| !f.is_ambiguous()));
+        assert_eq!(fixes(&faults[0].candidates[0]), vec![("c", -1)]);
+        assert_eq!(fixes(&faults[1].candidates[0]), vec![("g", -1)]);
+        assert_eq!(faults[0].caused_by.map(|p| p.name.as_str()), Some("c"));
+        assert!(faults[1].caused_by.is_none());
+        assert_eq!(faults[0].to_string(), "a (caused by \"c\")");
+    }
+
+    #[test]
+    fn caused_faults() {
+        let tower = build("a (1) -> b, c, d\nb (21)\nc (3) -> e, f, g\nd (21)\ne (6)\nf (6)\n\
+                           g (3) -> h, i, j\nh (1)\ni (1)\nj (2)");
+        let faults = report(&tower);
+        let names: Vec<&str> = faults.iter().map(|f| f.prog.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "c", "g"]);
+        let actionable: Vec<&Fault> = faults.iter().filter(|f| f.caused_by.is_none()).collect();
+        assert_eq!(actionable.len(), 1);
+        assert_eq!(actionable[0].prog.name, "g");
+        assert_eq!(fixes(&actionable[0].candidates[0]), vec![("j", -1)]);
+        assert_eq!(faults[1].to_string(), "c (caused by \"g\")");
+    }
+
+    #[test]
+    fn ambiguous_faults() {
+        let tower = build("a (1) -> b, c\nb (5)\nc (8)");
+        let faults = report(&tower);
+        assert!(faults[0].is_ambiguous());
+        assert_eq!(fixes(&faults[0].candidates[0]), vec![("c", -3)]);
+        assert_eq!(fixes(&faults[0].candidates[1]), vec![("b", 3)]);
+        assert_eq!(faults[0].to_string(),
+                   "a (ambiguous)\n   weight 5: set \"c\".own_weight=5 (-3)\n   weight 8: set \"b\".own_weight=8 (+3)");
+        assert!(find_unbalanced(&tower).is_none());
+
+        let tower = build("a (1) -> b, c, d, e\nb (5)\nc (5)\nd (8)\ne (8)");
+        assert_eq!(report(&tower)[0].candidates.len(), 2);
+    }
+
+    #[test]
+    fn tied_weights() {
+        let tower = build("a (1) -> b, c, d, e, f\nb (5)\nc (5)\nd (8)\ne (8)\nf (9)");
+        let faults = report(&tower);
+        assert!(faults[0].is_ambiguous());
+        let targets: Vec<u32> = faults[0].candidates.iter().map(|c| c.target_weight).collect();
+        assert_eq!(targets, vec![5, 8]);
+        assert_eq!(fixes(&faults[0].candidates[0]), vec![("d", -3), ("e", -3), ("f", -4)]);
+        assert_eq!(fixes(&faults[0].candidates[1]), vec![("b", 3), ("c", 3), ("f", -1)]);
+    }
+
+    #[test]
+    fn negative_own_weight() {
+        // "c" weighs 2 + 10, it can't get down to 5
+        let tower = build("a (1) -> b, c\nb (5)\nc (2) -> d, e\nd (5)\ne (5)");
+        let faults = report(&tower);
+        assert_eq!(faults[0].candidates.len(), 1);
+        assert_eq!(faults[0].candidates[0].target_weight, 12);
+        assert_eq!(fixes(&faults[0].candidates[0]), vec![("b", 7)]);
+
+        let tower = build("a (1) -> b, c, d\nb (5)\nc (5)\nd (2) -> e, f\ne (5)\nf (5)");
+        let faults = report(&tower);
+        assert!(faults[0].candidates.is_empty());
+        assert_eq!(faults[0].to_string(), "a (no valid fix)");
+    }
+}
@@ -0,0 +1,130 @@
+use std::fmt::Write;
+use super::{Prog, find_unbalanced};
+
+/// Progs that need highlighting: the unbalanced one and the one above it with
+/// the wrong weight, along with the own weight that would fix it
+struct Highlights<'a> {
+    unbalanced: Option<&'a Prog>,
+    wrong: Option<(&'a Prog, i64)>,
+}
+
+impl<'a> Highlights<'a> {
+    fn new(root: &'a Prog) -> Highlights<'a> {
+        let unbalanced = find_unbalanced(root);
+        let wrong = unbalanced.and_then(|p| {
+            let (diff, wrong) = p.diff_weight_above();
+            wrong.map(|w| (w, w.own_weight as i64 - diff))
+        });
+        Highlights { unbalanced, wrong }
+    }
+
+    fn is_unbalanced(&self, prog: &Prog) -> bool {
+        self.unbalanced.is_some_and(|p| std::ptr::eq(p, prog))
+    }
+
+    fn fix_for(&self, prog: &Prog) -> Option<i64> {
+        self.wrong.filter(|(p, _)| std::ptr::eq(*p, prog)).map(|(_, fix)| fix)
+    }
+}
+
+pub fn to_dot(root: &Prog) -> String {
+    let highlights = Highlights::new(root);
+    let mut dot = String::from("digraph tower {\n    rankdir=BT;\n    node [shape=box];\n");
+    let mut pending = vec![root];
+
+    while let Some(prog) = pending.pop() {
+        let name = escape(&prog.name);
+        write!(dot, "    \"{}\" [label=\"{}\\nown={} total={}", name, name, prog.own_weight, prog.weight()).unwrap();
+        if let Some(fix) = highlights.fix_for(prog) {
+            write!(dot, "\\nFIX: own={}\", style=filled, fillcolor=tomato", fix).unwrap();
+        } else if highlights.is_unbalanced(prog) {
+            dot += "\", style=filled, fillcolor=orange";
+        } else {
+            dot += "\"";
+        }
+        dot += "];\n";
+
+        for above in &prog.progs_above {
+            writeln!(dot, "    \"{}\" -> \"{}\";", escape(&above.name), name).unwrap();
+            pending.push(above);
+        }
+    }
+
+    dot += "}\n";
+    dot
+}
+
+pub fn to_json(root: &Prog) -> String {
+    let highlights = Highlights::new(root);
+    let mut json = String::new();
+    // iterative, keeping track of how many of the progs above are already written
+    let mut stack = vec![(root, 0)];
+
+    while let Some((prog, written)) = stack.pop() {
+        if written == 0 {
+            write!(json, "{{\"name\":\"{}\",\"own_weight\":{},\"total_weight\":{}",
+                   escape(&prog.name), prog.own_weight, prog.weight()).unwrap();
+            if highlights.is_unbalanced(prog) {
+                json += ",\"unbalanced\":true";
+            }
+            if let Some(fix) = highlights.fix_for(prog) {
+                write!(json, ",\"fix_own_weight\":{}", fix).unwrap();
+            }
+            json += ",\"progs_above\":[";
+        } else if written < prog.progs_above.len() {
+            json += ",";
+        }
+
+        match prog.progs_above.get(written) {
+            Some(above) => {
+                stack.push((prog, written + 1));
+                stack.push((above, 0));
+            },
+            None => json += "]}",
+        }
+    }
+
+    json
+}
+
+fn escape(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::{build_graph, parse_progs};
+    use std::fs::File;
+    use std::io::BufReader;
+
+    fn test_graph() -> Prog {
+        let file = File::open("input_test.txt").unwrap();
+        build_graph(&parse_progs(BufReader::new(file))).unwrap()
+    }
+
+    #[test]
+    fn dot_export() {
+        let dot = to_dot(&test_graph());
+        assert!(dot.starts_with("digraph tower {\n"));
+        assert!(dot.contains("\"tknk\" [label=\"tknk\\nown=41 total=778\", style=filled, fillcolor=orange];"));
+        assert!(dot.contains("\"ugml\" [label=\"ugml\\nown=68 total=251\\nFIX: own=60\", style=filled, fillcolor=tomato];"));
+        assert!(dot.contains("\"pbga\" [label=\"pbga\\nown=66 total=66\"];"));
+        assert!(dot.contains("\"pbga\" -> \"padx\";"));
+        assert_eq!(dot.matches(" -> ").count(), 12);
+    }
+
+    #[test]
+    fn json_export() {
+        let graph = parse_progs("a (1) -> b, c\nb (5)\nc (2) -> d\nd (3)".as_bytes());
+        let json = to_json(&build_graph(&graph).unwrap());
+        assert_eq!(json, concat!(
+            "{\"name\":\"a\",\"own_weight\":1,\"total_weight\":11,\"progs_above\":[",
+            "{\"name\":\"b\",\"own_weight\":5,\"total_weight\":5,\"progs_above\":[]},",
+            "{\"name\":\"c\",\"own_weight\":2,\"total_weight\":5,\"progs_above\":[",
+            "{\"name\":\"d\",\"own_weight\":3,\"total_weight\":3,\"progs_above\":[]}]}]}"));
+
+        let json = to_json(&test_graph());
+        assert!(json.contains("{\"name\":\"ugml\",\"own_weight\":68,\"total_weight\":251,\"fix_own_weight\":60,"));
+    }
+}
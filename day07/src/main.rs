@@ -1,28 +1,151 @@
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{HashMap, HashSet};
 use std::cell::Cell;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
 
-fn main() {
-    let file = File::open("input.txt").unwrap();
+mod balance;
+mod export;
+mod tower;
+
+use tower::Tower;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let file = File::open("input.txt")?;
     let reader = BufReader::new(file);
     let progs_info = parse_progs(reader);
 
-    let lowest = build_graph(&progs_info);
+    let lowest = build_graph(&progs_info)?;
     println!("Part1: lowest={}", lowest.name);
 
-    let unbalanced = find_unbalanced(&lowest).unwrap();
-    println!("Part2: unbalanced={}", unbalanced.name);
-    for prog in &unbalanced.progs_above {
-        println!("   {} ({}) -> {}", prog.name, prog.own_weight, prog.weight());
+    // pass --dot and/or --json to export the tower to tower.dot/tower.json,
+    // or --what-if to edit the tower interactively
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--dot" => fs::write("tower.dot", export::to_dot(&lowest))?,
+            "--json" => fs::write("tower.json", export::to_json(&lowest))?,
+            "--what-if" => return what_if(Tower::from_prog(&lowest)),
+            _ => return Err(format!("unknown argument {}", arg).into()),
+        }
+    }
+
+    match find_unbalanced(&lowest) {
+        Some(unbalanced) => {
+            println!("Part2: unbalanced={}", unbalanced.name);
+            for prog in &unbalanced.progs_above {
+                println!("   {} ({}) -> {}", prog.name, prog.own_weight, prog.weight());
+            }
+            let (diff, wrong_prog) = unbalanced.diff_weight_above();
+            let wrong_prog = wrong_prog.unwrap();
+            println!("-- FIX: set \"{}\".own_weight={}", wrong_prog.name, wrong_prog.own_weight as i64 - diff);
+        },
+        None => println!("Part2: error: no unbalanced prog with a single prog to fix"),
+    }
+
+    println!("Balance report:");
+    for fault in balance::report(&lowest) {
+        println!(" - {}", fault);
+    }
+
+    Ok(())
+}
+
+fn what_if(mut tower: Tower) -> Result<(), Box<dyn Error>> {
+    println!("Commands: show NAME | weight NAME W | add PARENT NAME W | remove NAME | move NAME PARENT | report");
+
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let weight = match words.as_slice() {
+            ["weight", _, w] | ["add", _, _, w] => match w.parse::<u32>() {
+                Ok(weight) => weight,
+                Err(e) => {
+                    println!("error: invalid weight \"{}\": {}", w, e);
+                    continue;
+                },
+            },
+            _ => 0,
+        };
+        let result = match words.as_slice() {
+            ["show", name] => show_prog(&tower, name),
+            ["weight", name, _] => tower.set_weight(name, weight),
+            ["add", parent, name, _] => tower.add_subtower(parent, &Prog::new(name, weight)),
+            ["remove", name] => tower.remove_subtower(name),
+            ["move", name, parent] => tower.reparent(name, parent),
+            ["report"] => {
+                balance::report(&tower.to_prog()).iter().for_each(|fault| println!(" - {}", fault));
+                Ok(())
+            },
+            [] => continue,
+            _ => {
+                println!("unknown command");
+                continue;
+            },
+        };
+
+        match (result, tower.find_unbalanced()) {
+            (Err(e), _) => println!("error: {}", e),
+            (Ok(_), Some(name)) => println!("unbalanced={}", name),
+            (Ok(_), None) => {
+                let root = tower.root();
+                println!("{} -> {} balanced={}", root, tower.total_weight(root)?, tower.is_balanced(root)?);
+            },
+        }
+    }
+
+    Ok(())
+}
+
+fn show_prog(tower: &Tower, name: &str) -> Result<(), tower::EditError> {
+    println!("{} ({}) -> {} balanced={}", name, tower.own_weight(name)?, tower.total_weight(name)?,
+             tower.is_balanced(name)?);
+    for (name_above, weight) in tower.weights_above(name)? {
+        println!("   {} -> {}", name_above, weight);
     }
-    let (diff, wrong_prog) = unbalanced.diff_weight_above();
-    let wrong_prog = wrong_prog.unwrap();
-    println!("-- FIX: set \"{}\".own_weight={}", wrong_prog.name, wrong_prog.own_weight - diff);
+    Ok(())
 }
 
 type ProgsInfo = HashMap<String,(u32,Vec<String>)>;
 
+#[derive(Debug, Default, PartialEq)]
+struct TowerError {
+    missing: Vec<(String, String)>, // (parent, unknown prog above it)
+    cycles: Vec<Vec<String>>,       // path starting and ending in the same prog
+    roots: Vec<String>,
+    multiple_parents: Vec<(String, Vec<String>)>, // (prog, all the progs below it)
+}
+
+impl TowerError {
+    fn is_valid(&self) -> bool {
+        self.missing.is_empty() && self.cycles.is_empty() && self.roots.len() == 1
+            && self.multiple_parents.is_empty()
+    }
+}
+
+impl fmt::Display for TowerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "invalid tower:")?;
+        for (parent, name) in &self.missing {
+            writeln!(f, "  missing prog \"{}\" (above \"{}\")", name, parent)?;
+        }
+        for cycle in &self.cycles {
+            writeln!(f, "  cycle: {}", cycle.join(" -> "))?;
+        }
+        for (name, parents) in &self.multiple_parents {
+            writeln!(f, "  prog \"{}\" above more than one prog: [{}]", name, parents.join(", "))?;
+        }
+        if self.roots.len() != 1 {
+            writeln!(f, "  expected 1 root, found {}: [{}]", self.roots.len(), self.roots.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for TowerError {}
+
 struct Prog {
     name: String,
     own_weight: u32,
@@ -51,77 +174,142 @@ impl Prog {
         result
     }
 
-    fn add_progs_above(&mut self, names: &Vec<String>, progs_info: &ProgsInfo) {
-        for name in names {
-            let (weight, names_above) = progs_info.get(name).unwrap();
-            let mut prog = Prog::new(name, *weight);
-            prog.add_progs_above(names_above, progs_info);
-            self.progs_above.push(prog);
+    /// Weight difference of the only prog above that doesn't weigh like the
+    /// others, if there is exactly one and it's not ambiguous which it is
+    fn diff_weight_above(&self) -> (i64, Option<&Prog>) {
+        let weights: Vec<(u32, u32)> = self.progs_above.iter().map(|p| (p.weight(), p.own_weight)).collect();
+        match balance::odd_one_out(&weights) {
+            Some((i, delta)) => (-delta, Some(&self.progs_above[i])),
+            None => (0, None),
         }
     }
-    
-    fn diff_weight_above(&self) -> (u32, Option<&Prog>) {
-        for p in &self.progs_above {
-            let mut equals_count = -1; // -1 to compensabe being equal to itself
-            let mut other = p;
-    
-            for p2 in &self.progs_above {
-                if p.weight() == p2.weight() {
-                    equals_count += 1;
-                }
-                else {
-                    other = p2;
-                }
-            }
+}
 
-            if equals_count == 0 {
-                return (p.weight() - other.weight(), Some(p));
-            }
+// Default drop is recursive and would overflow the stack with very deep towers
+impl Drop for Prog {
+    fn drop(&mut self) {
+        let mut pending = std::mem::take(&mut self.progs_above);
+        while let Some(mut prog) = pending.pop() {
+            pending.append(&mut prog.progs_above);
         }
-
-        (0, None)
     }
 }
 
 fn find_unbalanced(prog: &Prog) -> Option<&Prog> {
-    if let (_, Some(p)) = prog.diff_weight_above() {
-        match find_unbalanced(p) { // recursively search
-            Some(p_child) => return Some(p_child),
-            None => return Some(prog),
+    let mut unbalanced = None;
+    let mut prog = prog;
+
+    // follow the wrong progs up while they're unbalanced too
+    while let (_, Some(p)) = prog.diff_weight_above() {
+        unbalanced = Some(prog);
+        prog = p;
+    }
+
+    unbalanced
+}
+
+fn build_graph(progs_info: &ProgsInfo) -> Result<Prog, TowerError> {
+    let lowest_name = find_lowest(progs_info)?;
+    let (lowest_weight, lowest_children) = &progs_info[&lowest_name];
+
+    // iterative depth-first build: a prog is attached to its parent once all
+    // the progs above it are built, so its total weight can be cached already
+    let mut stack = vec![(Prog::new(&lowest_name, *lowest_weight), lowest_children.iter())];
+    loop {
+        let (_, names_above) = stack.last_mut().unwrap();
+        match names_above.next() {
+            Some(name) => {
+                let (weight, names_above) = &progs_info[name];
+                stack.push((Prog::new(name, *weight), names_above.iter()));
+            },
+            None => {
+                let (prog, _) = stack.pop().unwrap();
+                let total = prog.own_weight + prog.progs_above.iter().map(|p| p.weight()).sum::<u32>();
+                prog.total_weight.set(Some(total));
+                match stack.last_mut() {
+                    Some((parent, _)) => parent.progs_above.push(prog),
+                    None => return Ok(prog),
+                }
+            },
         }
     }
-    
-    None
 }
 
-fn build_graph(progs_info: &ProgsInfo) -> Prog {
-    let lowest_name = find_lowest(&progs_info);
-    let (lowest_weight, lowest_children) = progs_info.get(&lowest_name).unwrap();
-    
-    let mut lowest = Prog::new(&lowest_name, *lowest_weight);
-    lowest.add_progs_above(&lowest_children, progs_info);
+/// Find the bottom prog, validating that the tower is a single tree where all
+/// the progs above others exist, each one is above a single prog and there
+/// are no cycles
+fn find_lowest(progs_info: &ProgsInfo) -> Result<String, TowerError> {
+    let mut error = TowerError::default();
+    let mut names: Vec<&String> = progs_info.keys().collect();
+    names.sort();
+    // progs below each prog above others
+    let mut above_others: HashMap<&String, Vec<String>> = HashMap::new();
+
+    for name in &names {
+        for name_above in &progs_info[*name].1 {
+            if !progs_info.contains_key(name_above) {
+                error.missing.push((name.to_string(), name_above.to_string()));
+            }
+            above_others.entry(name_above).or_default().push(name.to_string());
+        }
+    }
 
-    lowest
+    error.roots = names.iter()
+        .filter(|name| !above_others.contains_key(**name))
+        .map(|name| name.to_string())
+        .collect();
+    error.multiple_parents = above_others.into_iter()
+        .filter(|(_, parents)| parents.len() > 1)
+        .map(|(name, parents)| (name.to_string(), parents))
+        .collect();
+    error.multiple_parents.sort();
+    error.cycles = find_cycles(progs_info, &names);
+
+    if error.is_valid() {
+        Ok(error.roots.pop().unwrap())
+    } else {
+        Err(error)
+    }
 }
 
-fn find_lowest(progs_info: &ProgsInfo) -> String {
-    let mut maybe_list = BTreeSet::new();
-    let mut deny_list  = BTreeSet::new();
+fn find_cycles(progs_info: &ProgsInfo, names: &[&String]) -> Vec<Vec<String>> {
+    let mut cycles = Vec::new();
+    let mut in_path = HashSet::new();
+    let mut visited = HashSet::new();
 
-    for (prog, (_, progs_above)) in progs_info {
-        if !deny_list.remove(prog) {
-            maybe_list.insert(String::from(prog));
+    for start in names {
+        if !visited.insert(*start) {
+            continue;
         }
-
-        for deny_prog in progs_above {
-            if !maybe_list.remove(deny_prog) {
-                deny_list.insert(String::from(deny_prog));
+        in_path.insert(*start);
+        let mut path = vec![(*start, progs_info[*start].1.iter())];
+
+        while let Some((name, names_above)) = path.last_mut() {
+            let name = *name;
+            match names_above.next() {
+                Some(above) if in_path.contains(above) => {
+                    let pos = path.iter().position(|(n, _)| *n == above).unwrap();
+                    let mut cycle: Vec<String> = path[pos..].iter().map(|(n, _)| n.to_string()).collect();
+                    cycle.push(above.to_string());
+                    cycles.push(cycle);
+                },
+                Some(above) => {
+                    if let Some((_, next_above)) = progs_info.get(above) {
+                        if visited.insert(above) {
+                            in_path.insert(above);
+                            path.push((above, next_above.iter()));
+                        }
+                    }
+                },
+                None => {
+                    in_path.remove(name);
+                    path.pop();
+                },
             }
         }
     }
 
-    assert!(maybe_list.len() == 1);
-    String::from(maybe_list.iter().next().unwrap())
+    cycles
 }
 
 fn parse_progs<T: BufRead>(reader: T) -> ProgsInfo {
@@ -191,7 +379,7 @@ mod test {
         let reader = BufReader::new(file);
         let progs_info = parse_progs(reader);
 
-        let lowest = find_lowest(&progs_info);
+        let lowest = find_lowest(&progs_info).unwrap();
         assert_eq!(lowest, "tknk");
     }
 
@@ -200,9 +388,53 @@ mod test {
         let file = File::open("input_test.txt").unwrap();
         let reader = BufReader::new(file);
         let progs_info = parse_progs(reader);
-        let graph = build_graph(&progs_info);
+        let graph = build_graph(&progs_info).unwrap();
 
         let unbalanced = find_unbalanced(&graph).unwrap();
         assert_eq!(unbalanced.name, "tknk");
     }
-}
\ No newline at end of file
+
+    fn progs_info_from(lines: &[&str]) -> ProgsInfo {
+        parse_progs(lines.join("\n").as_bytes())
+    }
+
+    #[test]
+    fn invalid_towers() {
+        let progs_info = progs_info_from(&["a (1) -> b, c", "b (2)"]);
+        let error = build_graph(&progs_info).err().unwrap();
+        assert_eq!(error.missing, vec![(String::from("a"), String::from("c"))]);
+        assert!(error.cycles.is_empty());
+
+        let progs_info = progs_info_from(&["a (1) -> b", "b (2) -> c", "c (3) -> d", "d (4) -> b"]);
+        let error = build_graph(&progs_info).err().unwrap();
+        assert_eq!(error.cycles, vec![vec!["b", "c", "d", "b"]]);
+        assert_eq!(error.roots, vec!["a"]);
+
+        let progs_info = progs_info_from(&["a (1) -> b", "b (2)", "c (3) -> d", "d (4)"]);
+        let error = build_graph(&progs_info).err().unwrap();
+        assert_eq!(error.roots, vec!["a", "c"]);
+
+        let progs_info = progs_info_from(&["a (1) -> a"]);
+        let error = build_graph(&progs_info).err().unwrap();
+        assert_eq!(error.cycles, vec![vec!["a", "a"]]);
+        assert!(error.roots.is_empty());
+
+        let progs_info = progs_info_from(&["a (1) -> b, c", "b (1) -> d", "c (1) -> d", "d (5)"]);
+        let error = build_graph(&progs_info).err().unwrap();
+        assert_eq!(error.multiple_parents, vec![(String::from("d"), vec![String::from("b"), String::from("c")])]);
+        assert!(error.missing.is_empty() && error.cycles.is_empty());
+        assert_eq!(error.roots, vec!["a"]);
+    }
+
+    #[test]
+    fn deep_tower() {
+        let depth = 100_000;
+        let mut lines: Vec<String> = (0..depth).map(|i| format!("p{} (1) -> p{}", i, i + 1)).collect();
+        lines.push(format!("p{} (1)", depth));
+        let progs_info = parse_progs(lines.join("\n").as_bytes());
+
+        let graph = build_graph(&progs_info).unwrap();
+        assert_eq!(graph.name, "p0");
+        assert_eq!(graph.weight(), depth + 1);
+    }
+}
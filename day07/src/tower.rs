@@ -0,0 +1,328 @@
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use super::{Prog, ProgsInfo, balance, build_graph};
+
+/// Editable tower. Unlike Prog, each node knows its parent, so when a weight
+/// changes only the cached total weights down to the bottom prog are updated.
+pub struct Tower {
+    nodes: Vec<Option<Node>>, // removed nodes leave an empty slot
+    ids: HashMap<String, usize>,
+    root: usize,
+}
+
+struct Node {
+    name: String,
+    own_weight: u32,
+    total_weight: u32,
+    parent: Option<usize>,
+    above: Vec<usize>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EditError {
+    UnknownProg(String),
+    DuplicatedProg(String),
+    BottomProg(String),
+    CycleCreated(String, String), // (prog, new parent above it)
+    WeightOverflow(String),       // prog whose total weight wouldn't fit in u32
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EditError::UnknownProg(name) => write!(f, "unknown prog \"{}\"", name),
+            EditError::DuplicatedProg(name) => write!(f, "prog \"{}\" already exists", name),
+            EditError::BottomProg(name) => write!(f, "\"{}\" is the bottom prog", name),
+            EditError::CycleCreated(name, parent) =>
+                write!(f, "\"{}\" can't be moved above \"{}\", which is above it", name, parent),
+            EditError::WeightOverflow(name) => write!(f, "total weight of \"{}\" would overflow", name),
+        }
+    }
+}
+
+impl Error for EditError {}
+
+impl Tower {
+    pub fn from_prog(root: &Prog) -> Tower {
+        let mut tower = Tower { nodes: Vec::new(), ids: HashMap::new(), root: 0 };
+        tower.insert(None, root);
+        tower
+    }
+
+    pub fn to_prog(&self) -> Prog {
+        build_graph(&self.progs_info()).unwrap()
+    }
+
+    pub fn progs_info(&self) -> ProgsInfo {
+        self.nodes.iter().flatten()
+            .map(|node| {
+                let names_above = node.above.iter().map(|&id| self.node(id).name.clone()).collect();
+                (node.name.clone(), (node.own_weight, names_above))
+            })
+            .collect()
+    }
+
+    pub fn root(&self) -> &str {
+        &self.node(self.root).name
+    }
+
+    pub fn own_weight(&self, name: &str) -> Result<u32, EditError> {
+        Ok(self.node(self.id(name)?).own_weight)
+    }
+
+    pub fn total_weight(&self, name: &str) -> Result<u32, EditError> {
+        Ok(self.node(self.id(name)?).total_weight)
+    }
+
+    pub fn weights_above(&self, name: &str) -> Result<Vec<(&str, u32)>, EditError> {
+        let node = self.node(self.id(name)?);
+        Ok(node.above.iter()
+            .map(|&id| (self.node(id).name.as_str(), self.node(id).total_weight))
+            .collect())
+    }
+
+    pub fn is_balanced(&self, name: &str) -> Result<bool, EditError> {
+        let weights = self.weights_above(name)?;
+        Ok(weights.windows(2).all(|w| w[0].1 == w[1].1))
+    }
+
+    pub fn set_weight(&mut self, name: &str, weight: u32) -> Result<(), EditError> {
+        let id = self.id(name)?;
+        let delta = weight as i64 - self.node(id).own_weight as i64;
+        self.check_totals(Some(id), delta, None)?;
+        self.node_mut(id).own_weight = weight;
+        self.propagate(Some(id), delta);
+        Ok(())
+    }
+
+    /// Place a copy of subtower above the parent prog
+    pub fn add_subtower(&mut self, parent: &str, subtower: &Prog) -> Result<(), EditError> {
+        let parent_id = self.id(parent)?;
+        let mut pending = vec![subtower];
+        while let Some(prog) = pending.pop() {
+            if self.ids.contains_key(&prog.name) {
+                return Err(EditError::DuplicatedProg(prog.name.clone()));
+            }
+            pending.extend(&prog.progs_above);
+        }
+        self.check_totals(Some(parent_id), subtower.weight() as i64, None)?;
+
+        let id = self.insert(Some(parent_id), subtower);
+        self.propagate(Some(parent_id), self.node(id).total_weight as i64);
+        Ok(())
+    }
+
+    /// Remove the prog and all the progs above it
+    pub fn remove_subtower(&mut self, name: &str) -> Result<(), EditError> {
+        let id = self.detach(name)?;
+        let mut pending = vec![id];
+        while let Some(id) = pending.pop() {
+            let node = self.nodes[id].take().unwrap();
+            self.ids.remove(&node.name);
+            pending.extend(node.above);
+        }
+        Ok(())
+    }
+
+    /// Move the prog, with all the progs above it, above new_parent
+    pub fn reparent(&mut self, name: &str, new_parent: &str) -> Result<(), EditError> {
+        let parent_id = self.id(new_parent)?;
+        let mut ancestor = Some(parent_id);
+        while let Some(id) = ancestor {
+            if self.node(id).name == name {
+                return Err(EditError::CycleCreated(name.to_string(), new_parent.to_string()));
+            }
+            ancestor = self.node(id).parent;
+        }
+
+        // the progs below both the old and new parents keep their weight
+        let id = self.id(name)?;
+        let mut old_ancestors = HashSet::new();
+        let mut ancestor = self.node(id).parent;
+        while let Some(i) = ancestor {
+            old_ancestors.insert(i);
+            ancestor = self.node(i).parent;
+        }
+        let mut common = Some(parent_id);
+        while let Some(i) = common.filter(|i| !old_ancestors.contains(i)) {
+            common = self.node(i).parent;
+        }
+        self.check_totals(Some(parent_id), self.node(id).total_weight as i64, common)?;
+
+        let id = self.detach(name)?;
+        self.node_mut(id).parent = Some(parent_id);
+        self.node_mut(parent_id).above.push(id);
+        self.propagate(Some(parent_id), self.node(id).total_weight as i64);
+        Ok(())
+    }
+
+    /// Same as find_unbalanced, but with the cached weights of the tower
+    pub fn find_unbalanced(&self) -> Option<&str> {
+        let mut unbalanced = None;
+        let mut id = self.root;
+
+        while let Some(wrong) = self.wrong_above(id) {
+            unbalanced = Some(self.node(id).name.as_str());
+            id = wrong;
+        }
+
+        unbalanced
+    }
+
+    fn wrong_above(&self, id: usize) -> Option<usize> {
+        let above = &self.node(id).above;
+        let weights: Vec<(u32, u32)> = above.iter()
+            .map(|&id| (self.node(id).total_weight, self.node(id).own_weight))
+            .collect();
+        balance::odd_one_out(&weights).map(|(i, _)| above[i])
+    }
+
+    fn insert(&mut self, parent: Option<usize>, prog: &Prog) -> usize {
+        let first_id = self.nodes.len();
+        let mut pending = vec![(parent, prog)];
+
+        while let Some((parent, prog)) = pending.pop() {
+            let id = self.nodes.len();
+            self.nodes.push(Some(Node {
+                name: prog.name.clone(), own_weight: prog.own_weight,
+                total_weight: prog.weight(), parent, above: Vec::new()
+            }));
+            self.ids.insert(prog.name.clone(), id);
+            if let Some(parent) = parent {
+                self.node_mut(parent).above.push(id);
+            }
+            pending.extend(prog.progs_above.iter().rev().map(|p| (Some(id), p)));
+        }
+
+        first_id
+    }
+
+    fn detach(&mut self, name: &str) -> Result<usize, EditError> {
+        let id = self.id(name)?;
+        let parent = self.node(id).parent.ok_or_else(|| EditError::BottomProg(name.to_string()))?;
+        self.node_mut(parent).above.retain(|&i| i != id);
+        self.node_mut(id).parent = None;
+        self.propagate(Some(parent), -(self.node(id).total_weight as i64));
+        Ok(id)
+    }
+
+    /// Check that adding delta to the total weights from id down to until
+    /// (excluded) doesn't overflow, before changing anything
+    fn check_totals(&self, mut id: Option<usize>, delta: i64, until: Option<usize>) -> Result<(), EditError> {
+        while let Some(i) = id.filter(|&i| Some(i) != until) {
+            let node = self.node(i);
+            if u32::try_from(node.total_weight as i64 + delta).is_err() {
+                return Err(EditError::WeightOverflow(node.name.clone()));
+            }
+            id = node.parent;
+        }
+        Ok(())
+    }
+
+    fn propagate(&mut self, mut id: Option<usize>, delta: i64) {
+        while let Some(i) = id {
+            let node = self.node_mut(i);
+            node.total_weight = (node.total_weight as i64 + delta) as u32;
+            id = node.parent;
+        }
+    }
+
+    fn id(&self, name: &str) -> Result<usize, EditError> {
+        self.ids.get(name).copied().ok_or_else(|| EditError::UnknownProg(name.to_string()))
+    }
+
+    fn node(&self, id: usize) -> &Node {
+        self.nodes[id].as_ref().unwrap()
+    }
+
+    fn node_mut(&mut self, id: usize) -> &mut Node {
+        self.nodes[id].as_mut().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::{find_unbalanced, parse_progs};
+    use std::fs::File;
+    use std::io::BufReader;
+
+    fn test_tower() -> Tower {
+        let file = File::open("input_test.txt").unwrap();
+        Tower::from_prog(&build_graph(&parse_progs(BufReader::new(file))).unwrap())
+    }
+
+    #[test]
+    fn reweight() {
+        let mut tower = test_tower();
+        assert_eq!(tower.root(), "tknk");
+        assert_eq!(tower.total_weight("tknk"), Ok(778));
+        assert_eq!(tower.find_unbalanced(), Some("tknk"));
+        assert_eq!(tower.is_balanced("tknk"), Ok(false));
+
+        tower.set_weight("ugml", 60).unwrap();
+        assert_eq!(tower.total_weight("ugml"), Ok(243));
+        assert_eq!(tower.total_weight("tknk"), Ok(770));
+        assert_eq!(tower.is_balanced("tknk"), Ok(true));
+        assert_eq!(tower.find_unbalanced(), None);
+
+        assert_eq!(tower.set_weight("nope", 1), Err(EditError::UnknownProg(String::from("nope"))));
+    }
+
+    #[test]
+    fn edit_subtowers() {
+        let mut tower = test_tower();
+        tower.remove_subtower("padx").unwrap();
+        assert_eq!(tower.total_weight("tknk"), Ok(535));
+        assert_eq!(tower.own_weight("pbga"), Err(EditError::UnknownProg(String::from("pbga"))));
+
+        let mut subtower = Prog::new("new", 5);
+        subtower.progs_above.push(Prog::new("newer", 3));
+        tower.add_subtower("fwft", &subtower).unwrap();
+        assert_eq!(tower.total_weight("fwft"), Ok(251));
+        assert_eq!(tower.total_weight("tknk"), Ok(543));
+        assert_eq!(tower.add_subtower("ugml", &subtower), Err(EditError::DuplicatedProg(String::from("new"))));
+
+        tower.reparent("fwft", "ugml").unwrap();
+        assert_eq!(tower.total_weight("ugml"), Ok(502));
+        assert_eq!(tower.total_weight("tknk"), Ok(543));
+        assert_eq!(tower.weights_above("tknk"), Ok(vec![("ugml", 502)]));
+        assert_eq!(tower.reparent("ugml", "newer"),
+                   Err(EditError::CycleCreated(String::from("ugml"), String::from("newer"))));
+        assert_eq!(tower.remove_subtower("tknk"), Err(EditError::BottomProg(String::from("tknk"))));
+
+        let prog = tower.to_prog();
+        assert_eq!(prog.weight(), 543);
+        assert_eq!(find_unbalanced(&prog).map(|p| p.name.clone()), tower.find_unbalanced().map(String::from));
+    }
+
+    #[test]
+    fn weight_overflow() {
+        let mut tower = test_tower();
+        let overflow = |name: &str| Err(EditError::WeightOverflow(String::from(name)));
+        assert_eq!(tower.set_weight("ugml", u32::MAX), overflow("ugml"));
+        assert_eq!(tower.set_weight("gyxo", u32::MAX - 300), overflow("tknk"));
+        assert_eq!(tower.add_subtower("gyxo", &Prog::new("new", u32::MAX - 100)), overflow("ugml"));
+        assert_eq!(tower.own_weight("ugml"), Ok(68));
+        assert_eq!(tower.total_weight("tknk"), Ok(778));
+        assert_eq!(tower.own_weight("new"), Err(EditError::UnknownProg(String::from("new"))));
+
+        // the totals above the common prog below both parents only go up
+        tower.set_weight("tknk", u32::MAX - 778 + 41).unwrap();
+        assert_eq!(tower.total_weight("tknk"), Ok(u32::MAX));
+        tower.reparent("ugml", "padx").unwrap();
+        assert_eq!(tower.total_weight("padx"), Ok(243 + 251));
+        assert_eq!(tower.total_weight("tknk"), Ok(u32::MAX));
+    }
+
+    #[test]
+    fn same_rule_as_prog() {
+        // the only fix for "d" would need an own weight of -5
+        let prog = build_graph(&parse_progs("a (1) -> b, c, d\nb (5)\nc (5)\nd (2) -> e, f\ne (5)\nf (5)".as_bytes())).unwrap();
+        let tower = Tower::from_prog(&prog);
+        assert!(find_unbalanced(&prog).is_none());
+        assert_eq!(tower.find_unbalanced(), None);
+    }
+}
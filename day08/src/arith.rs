@@ -0,0 +1,167 @@
+use std::fmt;
+use std::str::FromStr;
+use super::ArithKind;
+
+/// Type of the register values, wide enough for all the widths: the actual
+/// width used is set by ArithMode
+pub type Value = i128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Overflow {
+    #[default]
+    Checked,
+    Wrapping,
+    Saturating,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Width {
+    #[default]
+    I32,
+    I64,
+    I128,
+}
+
+/// How the operations behave when the result doesn't fit in the registers'
+/// width. Parsed from strings like "wrapping", "i64" or "saturating-i128".
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ArithMode {
+    pub overflow: Overflow,
+    pub width: Width,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ArithError {
+    DivisionByZero,
+    Overflow,
+}
+
+impl ArithKind {
+    /// New value of the register, with the given arithmetic mode
+    pub fn apply(self, reg_val: Value, val: Value, mode: ArithMode) -> Result<Value, ArithError> {
+        let result = match self {
+            ArithKind::Inc => reg_val.checked_add(val),
+            ArithKind::Dec => reg_val.checked_sub(val),
+            ArithKind::Mul => reg_val.checked_mul(val),
+            ArithKind::Div | ArithKind::Mod if val == 0 => return Err(ArithError::DivisionByZero),
+            ArithKind::Div => reg_val.checked_div(val),
+            ArithKind::Mod => reg_val.checked_rem(val),
+            ArithKind::Set => Some(val),
+            ArithKind::Min => Some(reg_val.min(val)),
+            ArithKind::Max => Some(reg_val.max(val)),
+        };
+
+        // only with i128 the result can overflow before fitting it to the width
+        let result = match (result, mode.overflow) {
+            (Some(result), _) => result,
+            (None, Overflow::Checked) => return Err(ArithError::Overflow),
+            (None, Overflow::Wrapping) => match self {
+                ArithKind::Inc => reg_val.wrapping_add(val),
+                ArithKind::Dec => reg_val.wrapping_sub(val),
+                ArithKind::Mul => reg_val.wrapping_mul(val),
+                ArithKind::Div => reg_val.wrapping_div(val),
+                _ => reg_val.wrapping_rem(val),
+            },
+            (None, Overflow::Saturating) => match self {
+                ArithKind::Inc => reg_val.saturating_add(val),
+                ArithKind::Dec => reg_val.saturating_sub(val),
+                ArithKind::Mul => reg_val.saturating_mul(val),
+                ArithKind::Div => reg_val.saturating_div(val),
+                _ => reg_val.wrapping_rem(val),
+            },
+        };
+
+        mode.fit(result)
+    }
+}
+
+impl ArithMode {
+    pub fn fit(self, val: Value) -> Result<Value, ArithError> {
+        let (min, max) = self.width.range();
+        if val >= min && val <= max {
+            return Ok(val);
+        }
+
+        match (self.overflow, self.width) {
+            (Overflow::Checked, _) => Err(ArithError::Overflow),
+            (Overflow::Wrapping, Width::I32) => Ok(val as i32 as Value),
+            (Overflow::Wrapping, Width::I64) => Ok(val as i64 as Value),
+            (Overflow::Wrapping, Width::I128) => Ok(val),
+            (Overflow::Saturating, _) => Ok(val.clamp(min, max)),
+        }
+    }
+}
+
+impl Width {
+    pub fn range(self) -> (Value, Value) {
+        match self {
+            Width::I32 => (i32::MIN as Value, i32::MAX as Value),
+            Width::I64 => (i64::MIN as Value, i64::MAX as Value),
+            Width::I128 => (i128::MIN, i128::MAX),
+        }
+    }
+}
+
+impl FromStr for ArithMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ArithMode, String> {
+        let mut mode = ArithMode::default();
+        for word in s.split('-') {
+            match word {
+                "checked" => mode.overflow = Overflow::Checked,
+                "wrapping" => mode.overflow = Overflow::Wrapping,
+                "saturating" => mode.overflow = Overflow::Saturating,
+                "i32" => mode.width = Width::I32,
+                "i64" => mode.width = Width::I64,
+                "i128" => mode.width = Width::I128,
+                _ => return Err(format!("invalid arithmetic mode \"{}\"", s)),
+            }
+        }
+        Ok(mode)
+    }
+}
+
+impl fmt::Display for ArithMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}-{:?}", self.overflow, self.width)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn mode(s: &str) -> ArithMode {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn mode_parsing() {
+        assert_eq!(mode("checked"), ArithMode::default());
+        assert_eq!(mode("i64"), ArithMode {overflow: Overflow::Checked, width: Width::I64});
+        assert_eq!(mode("saturating-i128"), ArithMode {overflow: Overflow::Saturating, width: Width::I128});
+        assert!("wrapping-i16".parse::<ArithMode>().is_err());
+    }
+
+    #[test]
+    fn overflows() {
+        let max32 = i32::MAX as Value;
+        let min32 = i32::MIN as Value;
+        assert_eq!(ArithKind::Inc.apply(max32, 1, mode("checked")), Err(ArithError::Overflow));
+        assert_eq!(ArithKind::Inc.apply(max32, 1, mode("wrapping")), Ok(min32));
+        assert_eq!(ArithKind::Inc.apply(max32, 1, mode("saturating")), Ok(max32));
+        assert_eq!(ArithKind::Inc.apply(max32, 1, mode("i64")), Ok(max32 + 1));
+        assert_eq!(ArithKind::Div.apply(min32, -1, mode("checked")), Err(ArithError::Overflow));
+        assert_eq!(ArithKind::Div.apply(min32, -1, mode("wrapping")), Ok(min32));
+        assert_eq!(ArithKind::Mod.apply(min32, -1, mode("checked")), Ok(0));
+        assert_eq!(ArithKind::Set.apply(0, max32 + 1, mode("checked")), Err(ArithError::Overflow));
+        assert_eq!(ArithKind::Mod.apply(1, 0, mode("wrapping")), Err(ArithError::DivisionByZero));
+
+        assert_eq!(ArithKind::Mul.apply(i128::MAX, 2, mode("checked-i128")), Err(ArithError::Overflow));
+        assert_eq!(ArithKind::Mul.apply(i128::MAX, 2, mode("wrapping-i128")), Ok(-2));
+        assert_eq!(ArithKind::Dec.apply(i128::MIN, 1, mode("saturating-i128")), Ok(i128::MIN));
+        assert_eq!(ArithKind::Div.apply(i128::MIN, -1, mode("saturating-i128")), Ok(i128::MAX));
+        assert_eq!(ArithKind::Mul.apply(i64::MAX as Value, -3, mode("saturating-i64")), Ok(i64::MIN as Value));
+    }
+}
@@ -0,0 +1,110 @@
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+use day08::*;
+
+const HELP: &str = "Enter instructions like \"a inc 1 if b < 5\", or commands:
+  :regs        show the registers
+  :reset       set all registers to 0 and clear the history
+  :load FILE   run all the instructions of FILE
+  :history     show the instructions run so far
+  :mode MODE   set the arithmetic mode, like \"wrapping\" or \"checked-i64\"
+  :quit        exit";
+
+struct Repl {
+    process: Process,
+    history: Vec<(String, bool)>, // (instruction, executed)
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut repl = Repl {process: Process::new(Registers::new()), history: Vec::new()};
+    println!("{}", HELP);
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("> ");
+        io::stdout().flush()?;
+
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => break,
+        };
+        let line = line.trim();
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        match words.as_slice() {
+            [] => (),
+            [":quit"] => break,
+            [":help"] => println!("{}", HELP),
+            [":regs"] => repl.print_registers(),
+            [":reset"] => {
+                repl.process.registers.values_mut().for_each(|value| *value = 0);
+                repl.history.clear();
+            },
+            [":load", filename] => match parse_input(filename) {
+                Ok((_, instructions)) => {
+                    let lines: Vec<String> = instructions.iter().map(|i| i.to_string()).collect();
+                    let mut count = 0;
+                    for (i, (instruction, line)) in instructions.iter().zip(lines).enumerate() {
+                        if let Err(e) = repl.run(instruction, line) {
+                            println!("error in {}: {}", filename, e.at_line(i + 1));
+                            break;
+                        }
+                        count += 1;
+                    }
+                    println!("{} instructions run", count);
+                },
+                Err(e) => println!("error: {}", e),
+            },
+            [":mode"] => println!("{}", repl.process.mode),
+            [":mode", mode] => match mode.parse() {
+                Ok(mode) => repl.process.mode = mode,
+                Err(e) => println!("error: {}", e),
+            },
+            [":history"] => {
+                for (i, (instruction, executed)) in repl.history.iter().enumerate() {
+                    println!("{:4} {} {}", i + 1, if *executed { ' ' } else { '-' }, instruction);
+                }
+            },
+            [cmd, ..] if cmd.starts_with(':') => println!("unknown command, type :help"),
+            _ => match parse_line(line) {
+                Ok(instruction) => match repl.run(&instruction, String::from(line)) {
+                    Ok(true) => {
+                        let reg_name = &instruction.operation.reg_name;
+                        println!("{}={}", reg_name, repl.process.reg_val(reg_name));
+                    },
+                    Ok(false) => (),
+                    Err(e) => println!("error: {}", e),
+                },
+                Err(e) => println!("error: {}", e),
+            },
+        }
+    }
+
+    Ok(())
+}
+
+impl Repl {
+    /// Run the instruction, returning whether it was executed or skipped
+    fn run(&mut self, instruction: &Instruction, line: String) -> Result<bool, ExecError> {
+        let failed = self.process.failed_comparisons(&instruction.condition);
+        if !failed.is_empty() {
+            let failed: Vec<String> = failed.iter()
+                .map(|cmp| format!("{} ({}={})", cmp, cmp.reg_name, self.process.reg_val(&cmp.reg_name)))
+                .collect();
+            println!("skipped \"{}\": false {}", line, failed.join(", "));
+            self.history.push((line, false));
+            return Ok(false);
+        }
+
+        self.process.execute(&instruction.operation)?;
+        self.history.push((line, true));
+        Ok(true)
+    }
+
+    fn print_registers(&self) {
+        for (name, val) in &self.process.registers {
+            println!("  {}={}", name, val);
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use super::{ArithKind, ArithMode, CmpKind, Condition, ExecError, Instruction, Operand, Registers, Value};
+
+/// Program lowered to bytecode, with registers assigned to dense indexes, to
+/// run it many times without looking up registers by name
+pub struct Program {
+    reg_names: Vec<String>,
+    reg_ids: HashMap<String, usize>,
+    code: Vec<Op>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Src {
+    Register(usize),
+    Number(Value),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    /// Continue if the comparison is true, jump to else_pc otherwise
+    Check { kind: CmpKind, reg: usize, src: Src, else_pc: usize },
+    Jump(usize),
+    Arith { kind: ArithKind, reg: usize, src: Src, line: usize },
+}
+
+impl Program {
+    pub fn compile(instructions: &[Instruction]) -> Program {
+        let mut program = Program { reg_names: Vec::new(), reg_ids: HashMap::new(), code: Vec::new() };
+
+        for (i, instruction) in instructions.iter().enumerate() {
+            let mut false_jumps = Vec::new();
+            program.compile_condition(&instruction.condition, &mut false_jumps);
+
+            let operation = &instruction.operation;
+            let reg = program.reg_id(&operation.reg_name);
+            let src = program.src(operation.operation.operand());
+            program.code.push(Op::Arith { kind: operation.operation.kind(), reg, src, line: i + 1 });
+            program.patch(&false_jumps);
+        }
+
+        program
+    }
+
+    /// Add the code to evaluate the condition: it continues after it if true,
+    /// and jumps to the pc that will be patched in false_jumps if false
+    fn compile_condition(&mut self, condition: &Condition, false_jumps: &mut Vec<usize>) {
+        match condition {
+            Condition::Cmp(cmp) => {
+                let reg = self.reg_id(&cmp.reg_name);
+                let src = self.src(cmp.condition.operand());
+                false_jumps.push(self.code.len());
+                self.code.push(Op::Check { kind: cmp.condition.kind(), reg, src, else_pc: 0 });
+            },
+            Condition::And(a, b) => {
+                self.compile_condition(a, false_jumps);
+                self.compile_condition(b, false_jumps);
+            },
+            Condition::Or(a, b) => {
+                let mut a_false_jumps = Vec::new();
+                self.compile_condition(a, &mut a_false_jumps);
+                let true_jump = self.code.len();
+                self.code.push(Op::Jump(0));
+                self.patch(&a_false_jumps);
+                self.compile_condition(b, false_jumps);
+                self.patch(&[true_jump]);
+            },
+        }
+    }
+
+    /// Make the jumps at the given pcs go to the next op to be added
+    fn patch(&mut self, jumps: &[usize]) {
+        let target = self.code.len();
+        for &pc in jumps {
+            match &mut self.code[pc] {
+                Op::Check { else_pc, .. } => *else_pc = target,
+                Op::Jump(pc) => *pc = target,
+                Op::Arith { .. } => unreachable!(),
+            }
+        }
+    }
+
+    fn reg_id(&mut self, name: &str) -> usize {
+        if let Some(&id) = self.reg_ids.get(name) {
+            return id;
+        }
+        self.reg_names.push(String::from(name));
+        self.reg_ids.insert(String::from(name), self.reg_names.len() - 1);
+        self.reg_names.len() - 1
+    }
+
+    fn src(&mut self, operand: &Operand) -> Src {
+        match operand {
+            Operand::Register(name) => Src::Register(self.reg_id(name)),
+            Operand::Number(n) => Src::Number(*n),
+        }
+    }
+
+    pub fn reg_names(&self) -> &[String] {
+        &self.reg_names
+    }
+
+    /// Registers for run, with the values of the registers map or 0
+    pub fn load_registers(&self, registers: &Registers) -> Vec<Value> {
+        self.reg_names.iter().map(|name| registers.get(name).copied().unwrap_or(0)).collect()
+    }
+
+    pub fn store_registers(&self, regs: &[Value], registers: &mut Registers) {
+        for (name, &val) in self.reg_names.iter().zip(regs) {
+            registers.insert(name.clone(), val);
+        }
+    }
+
+    pub fn run(&self, regs: &mut [Value], mode: ArithMode) -> Result<(), ExecError> {
+        let val = |regs: &[Value], src| match src {
+            Src::Register(reg) => regs[reg],
+            Src::Number(n) => n,
+        };
+
+        let mut pc = 0;
+        while pc < self.code.len() {
+            match self.code[pc] {
+                Op::Check { kind, reg, src, else_pc } => {
+                    if !kind.test(regs[reg], val(regs, src)) {
+                        pc = else_pc;
+                        continue;
+                    }
+                },
+                Op::Jump(target) => {
+                    pc = target;
+                    continue;
+                },
+                Op::Arith { kind, reg, src, line } => {
+                    regs[reg] = kind.apply(regs[reg], val(regs, src), mode)
+                        .map_err(|e| ExecError::new(e, &self.reg_names[reg]).at_line(line))?;
+                },
+            }
+            pc += 1;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::{ArithError, Process, parse_input, parse_line};
+
+    fn run_both(instructions: &[Instruction], mut registers: Registers, mode: ArithMode)
+            -> (Result<Registers, ExecError>, Result<Registers, ExecError>) {
+        for name in instructions.iter().flat_map(|i| i.reg_names()) {
+            registers.entry(String::from(name)).or_insert(0);
+        }
+        let mut process = Process::new(registers.clone());
+        process.mode = mode;
+        let interpreted = process.run(instructions).map(|_| process.registers);
+
+        let program = Program::compile(instructions);
+        let mut regs = program.load_registers(&registers);
+        let mut compiled_registers = registers;
+        let compiled = program.run(&mut regs, mode).map(|_| {
+            program.store_registers(&regs, &mut compiled_registers);
+            compiled_registers
+        });
+
+        (interpreted, compiled)
+    }
+
+    #[test]
+    fn compiled_input() {
+        let (registers, instructions) = parse_input("input.txt").unwrap();
+        let (interpreted, compiled) = run_both(&instructions, registers, ArithMode::default());
+        assert!(interpreted.is_ok());
+        assert_eq!(interpreted, compiled);
+    }
+
+    #[test]
+    fn compiled_conditions() {
+        let instructions: Vec<Instruction> = ["a set 1 if x == 0", "b set 2 if a == 1 and x == 1 or a == 1 and x == 0",
+                                              "c set 3 if a == 0 or b == 0 or x != 0", "d div x if a > b or c < b"]
+            .iter().map(|l| parse_line(l).unwrap()).collect();
+        let program = Program::compile(&instructions);
+        assert_eq!(program.reg_names(), ["x", "a", "b", "c", "d"]);
+
+        let mut regs = program.load_registers(&Registers::new());
+        assert_eq!(program.run(&mut regs, ArithMode::default()),
+                   Err(ExecError::new(ArithError::DivisionByZero, "d").at_line(4)));
+        assert_eq!(regs, vec![0, 1, 2, 0, 0]);
+    }
+
+    /// Compare the results of programs using all the operations, comparisons
+    /// and errors, in every arithmetic mode
+    #[test]
+    fn compiled_programs() {
+        let programs: [&[&str]; 6] = [
+            &["a inc 5 if b == 0", "b dec a if a != 0", "c mul b if b < 0", "c set 7 if c == 0 or a > 4",
+              "d div 2 if c >= 7", "a mod 3 if d <= 0", "b min -9 if a > 1 and c != 0", "d max b if b > -10"],
+            &["a set 1000 if a == 0", "a mul a if a > 0", "a mul a if a > 0", "a mul a if a > 0",
+              "a mul a if a > 0", "b set a if a != 0"],
+            &["a set -1000 if b == 0", "b set 999 if a < 0", "a mul b if a < b and b > 0", "a mul b if a < 0",
+              "a mul b if a < 0", "a mul b if a < 0", "a mul b if a < 0", "c inc 1 if a > 0 or a < 0"],
+            &["a inc 10 if x == 0", "b set a if a == 10", "b mod 3 if b > 3", "c div b if b != 0",
+              "d div c if x > 0 or x == 0", "e inc 1 if d == 0"],
+            &["a set 17 if a == 0", "b set -4 if b == 0", "c set a if a > b", "c mod b if c > 0",
+              "d set b if b < 0 and a > 0 or c == 1", "d min c if d <= c", "e max d if e >= d", "b mod 0 if e == 0"],
+            &["a set 2147483647 if a == 0", "a inc 1 if a > 0", "b set a if a < 0", "b dec 1 if b <= 0",
+              "c set 9223372036854775807 if c == 0", "c inc 1 if c >= 1"],
+        ];
+        let modes = ["checked", "wrapping", "saturating", "checked-i64", "wrapping-i128", "saturating-i128"];
+
+        for lines in programs.iter() {
+            let instructions: Vec<Instruction> = lines.iter().map(|l| parse_line(l).unwrap()).collect();
+            for mode in modes.iter() {
+                let (interpreted, compiled) = run_both(&instructions, Registers::new(), mode.parse().unwrap());
+                assert_eq!(interpreted, compiled, "mode {} {:#?}", mode, lines);
+            }
+        }
+    }
+}
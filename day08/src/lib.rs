@@ -0,0 +1,520 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+pub mod arith;
+pub mod compile;
+pub mod report;
+
+pub use arith::{ArithError, ArithMode, Value};
+
+pub type Registers = BTreeMap<String, Value>;
+pub type InstructionList = Vec<Instruction>;
+
+pub struct Process {
+    pub registers: Registers,
+    pub mode: ArithMode,
+}
+
+pub struct Instruction {
+    pub condition: Condition,
+    pub operation: Operation,
+}
+
+pub enum Condition {
+    Cmp(Comparison),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+}
+
+pub struct Comparison {
+    pub reg_name: String,
+    pub condition: CmpOp,
+}
+
+pub enum CmpOp {
+    Eq(Operand),
+    Neq(Operand),
+    Gt(Operand),
+    Gte(Operand),
+    Lt(Operand),
+    Lte(Operand),
+}
+
+pub struct Operation {
+    pub reg_name: String,
+    pub operation: ArithOp,
+}
+
+pub enum ArithOp {
+    Inc(Operand),
+    Dec(Operand),
+    Mul(Operand),
+    Div(Operand),
+    Mod(Operand),
+    Set(Operand),
+    Min(Operand),
+    Max(Operand),
+}
+
+/// Comparison and operation types, without operands
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CmpKind { Eq, Neq, Gt, Gte, Lt, Lte }
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArithKind { Inc, Dec, Mul, Div, Mod, Set, Min, Max }
+
+pub enum Operand {
+    Register(String),
+    Number(Value),
+}
+
+/// Error in the operation of register reg_name, at the given line if known
+#[derive(Debug, PartialEq)]
+pub struct ExecError {
+    pub kind: ArithError,
+    pub reg_name: String,
+    pub line: Option<usize>,
+}
+
+impl ExecError {
+    pub fn new(kind: ArithError, reg_name: &str) -> ExecError {
+        ExecError { kind, reg_name: String::from(reg_name), line: None }
+    }
+
+    pub fn at_line(self, line: usize) -> ExecError {
+        ExecError { line: Some(line), ..self }
+    }
+}
+
+impl fmt::Display for ExecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {}: ", line)?;
+        }
+        match self.kind {
+            ArithError::DivisionByZero => write!(f, "division by zero in register {}", self.reg_name),
+            ArithError::Overflow => write!(f, "overflow in register {}", self.reg_name),
+        }
+    }
+}
+
+impl Error for ExecError {}
+
+impl Process {
+    pub fn new(registers: Registers) -> Process {
+        Process { registers, mode: ArithMode::default() }
+    }
+
+    /// Run all the instructions, stopping at the first error
+    pub fn run(&mut self, instructions: &[Instruction]) -> Result<(), ExecError> {
+        for (i, instruction) in instructions.iter().enumerate() {
+            if self.evaluate(&instruction.condition) {
+                self.execute(&instruction.operation).map_err(|e| e.at_line(i + 1))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn evaluate(&self, condition: &Condition) -> bool {
+        match condition {
+            Condition::Cmp(cmp) => self.compare(cmp),
+            Condition::And(a, b) => self.evaluate(a) && self.evaluate(b),
+            Condition::Or(a, b) => self.evaluate(a) || self.evaluate(b),
+        }
+    }
+
+    pub fn compare(&self, comparison: &Comparison) -> bool {
+        let reg_val = self.reg_val(&comparison.reg_name);
+        let val = self.val(comparison.condition.operand());
+        comparison.condition.kind().test(reg_val, val)
+    }
+
+    /// Comparisons that made the condition false: the first false one of an
+    /// "and", or the ones of both sides of an "or"
+    pub fn failed_comparisons<'a>(&self, condition: &'a Condition) -> Vec<&'a Comparison> {
+        match condition {
+            Condition::Cmp(cmp) if !self.compare(cmp) => vec![cmp],
+            Condition::And(a, b) if !self.evaluate(a) => self.failed_comparisons(a),
+            Condition::And(_, b) => self.failed_comparisons(b),
+            Condition::Or(a, b) if !self.evaluate(condition) => {
+                let mut failed = self.failed_comparisons(a);
+                failed.extend(self.failed_comparisons(b));
+                failed
+            },
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn execute(&mut self, operation: &Operation) -> Result<(), ExecError> {
+        let val = self.val(operation.operation.operand());
+        let reg_val = self.registers.entry(operation.reg_name.clone()).or_insert(0);
+        *reg_val = operation.operation.kind().apply(*reg_val, val, self.mode)
+            .map_err(|e| ExecError::new(e, &operation.reg_name))?;
+        Ok(())
+    }
+
+    pub fn val(&self, operand: &Operand) -> Value {
+        match operand {
+            Operand::Register(name) => self.reg_val(name),
+            Operand::Number(n) => *n,
+        }
+    }
+
+    pub fn reg_val(&self, reg_name: &str) -> Value {
+        self.registers.get(reg_name).copied().unwrap_or(0)
+    }
+}
+
+impl Instruction {
+    /// Names of all the registers that the instruction reads or writes
+    pub fn reg_names(&self) -> Vec<&str> {
+        let mut names = vec![self.operation.reg_name.as_str()];
+        if let Operand::Register(name) = self.operation.operation.operand() {
+            names.push(name);
+        }
+        self.condition.add_reg_names(&mut names);
+        names
+    }
+}
+
+impl Condition {
+    fn add_reg_names<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match self {
+            Condition::Cmp(cmp) => {
+                names.push(&cmp.reg_name);
+                if let Operand::Register(name) = cmp.condition.operand() {
+                    names.push(name);
+                }
+            },
+            Condition::And(a, b) | Condition::Or(a, b) => {
+                a.add_reg_names(names);
+                b.add_reg_names(names);
+            },
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} if {}", self.operation, self.condition)
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Condition::Cmp(cmp) => write!(f, "{}", cmp),
+            Condition::And(a, b) => write!(f, "{} and {}", a, b),
+            Condition::Or(a, b) => write!(f, "{} or {}", a, b),
+        }
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let op = match self.condition {
+            CmpOp::Eq(_)  => "==",
+            CmpOp::Neq(_) => "!=",
+            CmpOp::Gt(_)  => ">",
+            CmpOp::Gte(_) => ">=",
+            CmpOp::Lt(_)  => "<",
+            CmpOp::Lte(_) => "<=",
+        };
+        write!(f, "{} {} {}", self.reg_name, op, self.condition.operand())
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let op = match self.operation {
+            ArithOp::Inc(_) => "inc",
+            ArithOp::Dec(_) => "dec",
+            ArithOp::Mul(_) => "mul",
+            ArithOp::Div(_) => "div",
+            ArithOp::Mod(_) => "mod",
+            ArithOp::Set(_) => "set",
+            ArithOp::Min(_) => "min",
+            ArithOp::Max(_) => "max",
+        };
+        write!(f, "{} {} {}", self.reg_name, op, self.operation.operand())
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operand::Register(name) => write!(f, "{}", name),
+            Operand::Number(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+impl CmpKind {
+    pub fn test(self, reg_val: Value, val: Value) -> bool {
+        match self {
+            CmpKind::Eq  => reg_val == val,
+            CmpKind::Neq => reg_val != val,
+            CmpKind::Gt  => reg_val > val,
+            CmpKind::Gte => reg_val >= val,
+            CmpKind::Lt  => reg_val < val,
+            CmpKind::Lte => reg_val <= val,
+        }
+    }
+}
+
+impl CmpOp {
+    pub fn kind(&self) -> CmpKind {
+        match self {
+            CmpOp::Eq(_)  => CmpKind::Eq,
+            CmpOp::Neq(_) => CmpKind::Neq,
+            CmpOp::Gt(_)  => CmpKind::Gt,
+            CmpOp::Gte(_) => CmpKind::Gte,
+            CmpOp::Lt(_)  => CmpKind::Lt,
+            CmpOp::Lte(_) => CmpKind::Lte,
+        }
+    }
+
+    pub fn operand(&self) -> &Operand {
+        match self {
+            CmpOp::Eq(v) | CmpOp::Neq(v) | CmpOp::Gt(v) |
+            CmpOp::Gte(v) | CmpOp::Lt(v) | CmpOp::Lte(v) => v,
+        }
+    }
+}
+
+impl ArithOp {
+    pub fn kind(&self) -> ArithKind {
+        match self {
+            ArithOp::Inc(_) => ArithKind::Inc,
+            ArithOp::Dec(_) => ArithKind::Dec,
+            ArithOp::Mul(_) => ArithKind::Mul,
+            ArithOp::Div(_) => ArithKind::Div,
+            ArithOp::Mod(_) => ArithKind::Mod,
+            ArithOp::Set(_) => ArithKind::Set,
+            ArithOp::Min(_) => ArithKind::Min,
+            ArithOp::Max(_) => ArithKind::Max,
+        }
+    }
+
+    pub fn operand(&self) -> &Operand {
+        match self {
+            ArithOp::Inc(v) | ArithOp::Dec(v) | ArithOp::Mul(v) | ArithOp::Div(v) |
+            ArithOp::Mod(v) | ArithOp::Set(v) | ArithOp::Min(v) | ArithOp::Max(v) => v,
+        }
+    }
+}
+
+pub fn parse_input(filename: &str) -> Result<(Registers, InstructionList), Box<dyn Error>> {
+    let f = File::open(filename)?;
+    let rdr = BufReader::new(f);
+
+    let mut registers = Registers::new();
+    let mut instructions = InstructionList::new();
+
+    for (i, line) in rdr.lines().enumerate() {
+        let instruction = parse_line(&line?).map_err(|e| format!("line {}: {}", i + 1, e))?;
+        for reg_name in instruction.reg_names() {
+            registers.insert(String::from(reg_name), 0);
+        }
+        instructions.push(instruction);
+    }
+
+    Ok((registers, instructions))
+}
+
+/// Parse "REG OP VAL if REG CMP VAL", where VAL can be a number or a register
+/// and the condition can be a list of comparisons joined with "and"/"or"
+/// ("and" takes precedence)
+pub fn parse_line(line: &str) -> Result<Instruction, String> {
+    let tokens: Vec<&str> = line.split_ascii_whitespace().collect();
+
+    if tokens.len() < 7 || tokens[3] != "if" {
+        return Err(format!("invalid instruction \"{}\"", line));
+    }
+
+    let mut condition = None;
+    for or_tokens in tokens[4..].split(|&t| t == "or") {
+        let mut and_condition = None;
+        for cmp_tokens in or_tokens.split(|&t| t == "and") {
+            let cmp = Condition::Cmp(parse_comparison(cmp_tokens)?);
+            and_condition = Some(match and_condition {
+                Some(prev) => Condition::And(Box::new(prev), Box::new(cmp)),
+                None => cmp,
+            });
+        }
+        let and_condition = and_condition.unwrap();
+        condition = Some(match condition {
+            Some(prev) => Condition::Or(Box::new(prev), Box::new(and_condition)),
+            None => and_condition,
+        });
+    }
+    let condition = condition.unwrap();
+
+    let reg = parse_register(tokens[0])?;
+    let val = parse_operand(tokens[2])?;
+    let op  = match tokens[1] {
+        "inc" => ArithOp::Inc(val),
+        "dec" => ArithOp::Dec(val),
+        "mul" => ArithOp::Mul(val),
+        "div" => ArithOp::Div(val),
+        "mod" => ArithOp::Mod(val),
+        "set" => ArithOp::Set(val),
+        "min" => ArithOp::Min(val),
+        "max" => ArithOp::Max(val),
+        op => return Err(format!("unknown operation \"{}\"", op)),
+    };
+    let operation = Operation {
+        reg_name: String::from(reg),
+        operation: op,
+    };
+
+    Ok(Instruction {condition, operation})
+}
+
+fn parse_comparison(tokens: &[&str]) -> Result<Comparison, String> {
+    if tokens.len() != 3 {
+        return Err(format!("invalid comparison \"{}\"", tokens.join(" ")));
+    }
+
+    let reg = parse_register(tokens[0])?;
+    let val = parse_operand(tokens[2])?;
+    let op  = match tokens[1] {
+        "==" => CmpOp::Eq(val),
+        "!=" => CmpOp::Neq(val),
+        ">"  => CmpOp::Gt(val),
+        ">=" => CmpOp::Gte(val),
+        "<"  => CmpOp::Lt(val),
+        "<=" => CmpOp::Lte(val),
+        op => return Err(format!("unknown comparison \"{}\"", op)),
+    };
+
+    Ok(Comparison {
+        reg_name: String::from(reg),
+        condition: op,
+    })
+}
+
+/// Numbers start with a digit or a sign, anything else must be a register
+fn parse_operand(token: &str) -> Result<Operand, String> {
+    if token.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '+') {
+        token.parse::<Value>().map(Operand::Number).map_err(|e| format!("invalid number \"{}\": {}", token, e))
+    } else {
+        parse_register(token).map(|reg| Operand::Register(String::from(reg)))
+    }
+}
+
+/// Register names are made of lowercase letters and underscores
+fn parse_register(token: &str) -> Result<&str, String> {
+    if token.chars().all(|c| c.is_ascii_lowercase() || c == '_') {
+        Ok(token)
+    } else {
+        Err(format!("invalid register \"{}\"", token))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn run(lines: &[&str]) -> Result<Registers, ExecError> {
+        let mut process = Process::new(Registers::new());
+        for line in lines {
+            let instruction = parse_line(line).unwrap();
+            if process.evaluate(&instruction.condition) {
+                process.execute(&instruction.operation)?;
+            }
+        }
+        Ok(process.registers)
+    }
+
+    #[test]
+    fn original_instructions() {
+        let regs = run(&["b inc 5 if a > 1", "a inc 1 if b < 5", "c dec -10 if a >= 1", "c inc -20 if c == 10"])
+            .unwrap();
+        assert_eq!(regs.get("a"), Some(&1));
+        assert_eq!(regs.get("b"), None);
+        assert_eq!(regs.get("c"), Some(&-10));
+    }
+
+    #[test]
+    fn extended_operations() {
+        let regs = run(&["a set 7 if x == 0", "b set a if a == 7", "b mul b if b != a", "b mul a if b == a",
+                         "c set b if a < b", "c div 2 if c >= 24", "d set c if c <= 24", "d mod 5 if x == 0",
+                         "e min -3 if d == 4", "f max e if f > e"]).unwrap();
+        let values: Vec<Value> = regs.values().copied().collect();
+        assert_eq!(values, vec![7, 49, 24, 4, -3, 0]);
+
+        assert_eq!(run(&["a div b if a == 0"]), Err(ExecError::new(ArithError::DivisionByZero, "a")));
+    }
+
+    #[test]
+    fn overflow_modes() {
+        let instructions: Vec<Instruction> = ["a set 2147483647 if a == 0", "b dec 1 if a > 0", "a inc 1 if b < 0"]
+            .iter().map(|l| parse_line(l).unwrap()).collect();
+
+        let mut process = Process::new(Registers::new());
+        let error = process.run(&instructions).err().unwrap();
+        assert_eq!(error, ExecError::new(ArithError::Overflow, "a").at_line(3));
+        assert_eq!(error.to_string(), "line 3: overflow in register a");
+
+        for (mode, expects) in [("wrapping", -2147483648), ("saturating", 2147483647), ("i64", 2147483648)].iter() {
+            let mut process = Process::new(Registers::new());
+            process.mode = mode.parse().unwrap();
+            process.run(&instructions).unwrap();
+            assert_eq!(process.reg_val("a"), *expects, "mode {}", mode);
+        }
+    }
+
+    #[test]
+    fn compound_conditions() {
+        let regs = run(&["a set 1 if x == 0", "b set 1 if a == 1 and x == 1 or a == 1 and x == 0",
+                         "c set 1 if a == 0 or b == 0 or x != 0", "d set 1 if a == 1 and b == 1 and x == 0"])
+            .unwrap();
+        assert_eq!(regs.get("b"), Some(&1));
+        assert_eq!(regs.get("c"), None);
+        assert_eq!(regs.get("d"), Some(&1));
+
+        assert!(parse_line("a inc 1 if b > 1 and").is_err());
+        assert!(parse_line("a inc 1 if b ~ 1").is_err());
+        assert!(parse_line("a add 1 if b > 1").is_err());
+    }
+
+    #[test]
+    fn invalid_operands() {
+        let error = |line| parse_line(line).err().unwrap();
+        assert_eq!(error("x inc 1.5 if y > 0"), "invalid number \"1.5\": invalid digit found in string");
+        assert_eq!(error("x inc 1 if y > 1000000000000000000000000000000000000000000"), "invalid number \"1000000000000000000000000000000000000000000\": number too large to fit in target type");
+        assert_eq!(error("x inc 1 if y > -"), "invalid number \"-\": invalid digit found in string");
+        assert_eq!(error("5 inc 1 if a > 0"), "invalid register \"5\"");
+        assert_eq!(error("x inc 1 if a2 > 0"), "invalid register \"a2\"");
+        assert_eq!(error("x inc y.z if a > 0"), "invalid register \"y.z\"");
+        assert!(parse_line("x_y inc -1 if a_b > +1").is_ok());
+    }
+
+    #[test]
+    fn failed_condition() {
+        let process = Process::new(vec![(String::from("a"), 1)].into_iter().collect());
+        let failed = |line| {
+            let instruction = parse_line(line).unwrap();
+            process.failed_comparisons(&instruction.condition).iter().map(|c| c.to_string()).collect::<Vec<_>>()
+        };
+        assert_eq!(failed("x inc 1 if a == 1 and b > 0 and a < 0"), vec!["b > 0"]);
+        assert_eq!(failed("x inc 1 if a == 2 or b > a"), vec!["a == 2", "b > a"]);
+        assert_eq!(failed("x inc 1 if a == 2 or b < a"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn instruction_display() {
+        let line = "a max b if c > -1 or d == e and f != 2";
+        assert_eq!(parse_line(line).unwrap().to_string(), line);
+    }
+
+    #[test]
+    fn registers_referenced() {
+        let instruction = parse_line("a inc b if c > d or e < 1").unwrap();
+        assert_eq!(instruction.reg_names(), vec!["a", "b", "c", "d", "e"]);
+    }
+}
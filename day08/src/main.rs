@@ -1,140 +1,38 @@
-use std::collections::BTreeMap;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-
-type Registers = BTreeMap<String, i32>;
-type InstructionList = Vec<Instruction>;
-
-struct Process {
-    registers: Registers,
-}
-
-struct Instruction {
-    condition: Condition,
-    operation: Operation,
-}
-
-struct Condition {
-    reg_name: String,
-    condition: CmpOp,
-}
-
-enum CmpOp {
-    Eq(i32),
-    Neq(i32),
-    Gt(i32),
-    Gte(i32),
-    Lt(i32),
-    Lte(i32),
-}
-
-struct Operation {
-    reg_name: String,
-    operation: ArithOp,
-}
-
-enum ArithOp {
-    Inc(i32),
-    Dec(i32),
-}
-
-fn main() {
-    let (registers, instructions) = parse_input();
-    let mut process = Process {registers};
-
-    let mut max_during = 0;
-
-    for instruction in &instructions {
-        if process.evaluate(&instruction.condition) {
-            process.execute(&instruction.operation);
-
-            let reg_name: &str = &instruction.operation.reg_name;
-            let reg_val = *process.registers.get(reg_name).unwrap();
-            if reg_val > max_during { max_during = reg_val; }
-        }
+use std::error::Error;
+use std::fs;
+use day08::*;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    // --report prints the execution report, --csv saves it in report_*.csv,
+    // --trace adds the trace of all the instructions to them, and
+    // --arith MODE sets the arithmetic mode, like "wrapping" or "checked-i64"
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let has_arg = |arg: &str| args.iter().any(|a| a == arg);
+
+    let (registers, instructions) = parse_input("input.txt")?;
+    let mut process = Process::new(registers);
+    if let Some(pos) = args.iter().position(|a| a == "--arith") {
+        process.mode = args.get(pos + 1).ok_or("missing arithmetic mode")?.parse()?;
     }
 
-    let mut max_after = i32::MIN;
-    for &v in process.registers.values() {
-        if v > max_after { max_after = v; }
-    }
+    let report = report::run(&mut process, &instructions, has_arg("--trace"))?;
+
+    let max_after = report.registers.values().map(|stats| stats.last).max().unwrap_or(Value::MIN);
+    let max_during = report.registers.values().map(|stats| stats.max).max().unwrap_or(0);
 
     println!("Part1: max after finish={}", max_after);
     println!("Part2: max while running={}", max_during);
-}
 
-impl Process {
-    fn evaluate(&self, condition: &Condition) -> bool {
-        let reg_val = *self.registers.get(&condition.reg_name).unwrap();
-        match condition.condition {
-            CmpOp::Eq(v)  => reg_val == v,
-            CmpOp::Neq(v) => reg_val != v,
-            CmpOp::Gt(v)  => reg_val > v,
-            CmpOp::Gte(v) => reg_val >= v,
-            CmpOp::Lt(v)  => reg_val < v,
-            CmpOp::Lte(v) => reg_val <= v,
-        }
+    if has_arg("--report") {
+        print!("{}", report.to_text(&instructions));
     }
-
-    fn execute(&mut self, operation: &Operation) {
-        let reg_val = self.registers.get_mut(&operation.reg_name).unwrap();
-        match operation.operation {
-            ArithOp::Inc(v) => *reg_val += v,
-            ArithOp::Dec(v) => *reg_val -= v,
-        };
-    }
-}
-
-fn parse_input() -> (Registers, InstructionList) {
-    let f = File::open("input.txt").unwrap();
-    let rdr = BufReader::new(f);
-
-    let mut registers = Registers::new();
-    let mut instructions = InstructionList::new();
-
-    for line in rdr.lines().map(|l| l.unwrap()) {
-        let instruction = parse_line(&line);
-        registers.insert(String::from(&instruction.condition.reg_name), 0);
-        registers.insert(String::from(&instruction.operation.reg_name), 0);
-        instructions.push(instruction);
+    if has_arg("--csv") {
+        fs::write("report_instructions.csv", report.instructions_csv())?;
+        fs::write("report_registers.csv", report.registers_csv())?;
+        if let Some(trace) = report.trace_csv() {
+            fs::write("report_trace.csv", trace)?;
+        }
     }
 
-    (registers, instructions)
+    Ok(())
 }
-
-fn parse_line(line: &str) -> Instruction {
-    let tokens: Vec<&str> = line.split_ascii_whitespace().collect();
-
-    assert_eq!(tokens.len(), 7);
-    assert_eq!(tokens[3], "if");
-
-    let reg = tokens[4];
-    let val = tokens[6].parse::<i32>().unwrap();
-    let op  = match tokens[5] {
-        "==" => CmpOp::Eq(val),
-        "!=" => CmpOp::Neq(val),
-        ">"  => CmpOp::Gt(val),
-        ">=" => CmpOp::Gte(val),
-        "<"  => CmpOp::Lt(val),
-        "<=" => CmpOp::Lte(val),
-        _ => panic!(),
-    };
-    let condition = Condition {
-        reg_name: String::from(reg),
-        condition: op,
-    };
-
-    let reg = tokens[0];
-    let val = tokens[2].parse::<i32>().unwrap();
-    let op  = match tokens[1] {
-        "inc" => ArithOp::Inc(val),
-        "dec" => ArithOp::Dec(val),
-        _ => panic!(),
-    };
-    let operation = Operation {
-        reg_name: String::from(reg),
-        operation: op,
-    };
-
-    Instruction {condition, operation}
-}
\ No newline at end of file
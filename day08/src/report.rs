@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use super::{ExecError, Instruction, Process, Value};
+
+/// Extreme values of a register. The "at" fields are the indexes of the
+/// instructions that set them, or None if it's the value before starting.
+#[derive(Debug, PartialEq)]
+pub struct RegisterStats {
+    pub min: Value,
+    pub min_at: Option<usize>,
+    pub max: Value,
+    pub max_at: Option<usize>,
+    pub last: Value,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct TraceStep {
+    pub index: usize,
+    pub executed: bool,
+    pub before: Value,
+    pub after: Value,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ExecutionReport {
+    pub hits: Vec<u32>,
+    pub skips: Vec<u32>,
+    pub registers: BTreeMap<String, RegisterStats>,
+    pub trace: Option<Vec<TraceStep>>,
+}
+
+/// Run all the instructions, collecting statistics and, optionally, a trace
+/// with the value of the operation's register before and after each one
+pub fn run(process: &mut Process, instructions: &[Instruction], trace: bool) -> Result<ExecutionReport, ExecError> {
+    let mut report = ExecutionReport {
+        hits: vec![0; instructions.len()],
+        skips: vec![0; instructions.len()],
+        registers: BTreeMap::new(),
+        trace: if trace { Some(Vec::new()) } else { None },
+    };
+    for (name, &val) in &process.registers {
+        report.registers.insert(name.clone(), RegisterStats::new(val));
+    }
+
+    for (index, instruction) in instructions.iter().enumerate() {
+        let reg_name = &instruction.operation.reg_name;
+        let before = process.reg_val(reg_name);
+        let executed = process.evaluate(&instruction.condition);
+
+        if executed {
+            process.execute(&instruction.operation).map_err(|e| e.at_line(index + 1))?;
+            report.hits[index] += 1;
+            let after = process.reg_val(reg_name);
+            report.registers.entry(reg_name.clone())
+                .or_insert_with(|| RegisterStats::new(before))
+                .update(after, index);
+        } else {
+            report.skips[index] += 1;
+        }
+
+        if let Some(trace) = &mut report.trace {
+            trace.push(TraceStep {index, executed, before, after: process.reg_val(reg_name)});
+        }
+    }
+
+    Ok(report)
+}
+
+impl RegisterStats {
+    fn new(val: Value) -> RegisterStats {
+        RegisterStats {min: val, min_at: None, max: val, max_at: None, last: val}
+    }
+
+    fn update(&mut self, val: Value, index: usize) {
+        if val < self.min {
+            self.min = val;
+            self.min_at = Some(index);
+        }
+        if val > self.max {
+            self.max = val;
+            self.max_at = Some(index);
+        }
+        self.last = val;
+    }
+}
+
+// instructions are shown by line number, starting at 1
+fn line_of(index: Option<usize>) -> String {
+    index.map_or(String::from("-"), |i| (i + 1).to_string())
+}
+
+impl ExecutionReport {
+    pub fn to_text(&self, instructions: &[Instruction]) -> String {
+        let mut text = String::from("Instructions:\n");
+        for (i, instruction) in instructions.iter().enumerate() {
+            writeln!(text, "  {:4} hits={} skips={}  {}", i + 1, self.hits[i], self.skips[i], instruction).unwrap();
+        }
+
+        text += "Registers:\n";
+        for (name, stats) in &self.registers {
+            writeln!(text, "  {:5} min={} (line {}) max={} (line {}) final={}", name,
+                     stats.min, line_of(stats.min_at), stats.max, line_of(stats.max_at), stats.last).unwrap();
+        }
+
+        if let Some(trace) = &self.trace {
+            text += "Trace:\n";
+            for step in trace {
+                let instruction = &instructions[step.index];
+                if step.executed {
+                    writeln!(text, "  {:4} {}: {} -> {}", step.index + 1, instruction.operation.reg_name,
+                             step.before, step.after).unwrap();
+                } else {
+                    writeln!(text, "  {:4} skipped", step.index + 1).unwrap();
+                }
+            }
+        }
+
+        text
+    }
+
+    pub fn instructions_csv(&self) -> String {
+        let mut csv = String::from("line,hits,skips\n");
+        for (i, (hits, skips)) in self.hits.iter().zip(&self.skips).enumerate() {
+            writeln!(csv, "{},{},{}", i + 1, hits, skips).unwrap();
+        }
+        csv
+    }
+
+    pub fn registers_csv(&self) -> String {
+        let mut csv = String::from("register,min,min_line,max,max_line,final\n");
+        for (name, stats) in &self.registers {
+            writeln!(csv, "{},{},{},{},{},{}", name, stats.min, line_of(stats.min_at),
+                     stats.max, line_of(stats.max_at), stats.last).unwrap();
+        }
+        csv
+    }
+
+    pub fn trace_csv(&self) -> Option<String> {
+        let trace = self.trace.as_ref()?;
+        let mut csv = String::from("line,executed,before,after\n");
+        for step in trace {
+            writeln!(csv, "{},{},{},{}", step.index + 1, step.executed, step.before, step.after).unwrap();
+        }
+        Some(csv)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::{Registers, parse_line};
+
+    fn test_run(trace: bool) -> (Vec<Instruction>, ExecutionReport) {
+        let instructions: Vec<Instruction> = ["b inc 5 if a > 1", "a inc 1 if b < 5", "c dec -10 if a >= 1",
+                                              "c inc -20 if c == 10"]
+            .iter().map(|l| parse_line(l).unwrap()).collect();
+        let mut process = Process::new(Registers::new());
+        for name in ["a", "b", "c"].iter() {
+            process.registers.insert(String::from(*name), 0);
+        }
+        let report = run(&mut process, &instructions, trace).unwrap();
+        (instructions, report)
+    }
+
+    #[test]
+    fn statistics() {
+        let (_, report) = test_run(false);
+        assert_eq!(report.hits, vec![0, 1, 1, 1]);
+        assert_eq!(report.skips, vec![1, 0, 0, 0]);
+        assert_eq!(report.registers["a"], RegisterStats {min: 0, min_at: None, max: 1, max_at: Some(1), last: 1});
+        assert_eq!(report.registers["b"], RegisterStats {min: 0, min_at: None, max: 0, max_at: None, last: 0});
+        assert_eq!(report.registers["c"], RegisterStats {min: -10, min_at: Some(3), max: 10, max_at: Some(2), last: -10});
+        assert!(report.trace.is_none());
+        assert!(report.trace_csv().is_none());
+    }
+
+    #[test]
+    fn outputs() {
+        let (instructions, report) = test_run(true);
+        assert_eq!(report.trace.as_ref().unwrap()[3], TraceStep {index: 3, executed: true, before: 10, after: -10});
+
+        assert_eq!(report.instructions_csv(), "line,hits,skips\n1,0,1\n2,1,0\n3,1,0\n4,1,0\n");
+        assert_eq!(report.registers_csv(),
+                   "register,min,min_line,max,max_line,final\na,0,-,1,2,1\nb,0,-,0,-,0\nc,-10,4,10,3,-10\n");
+        assert_eq!(report.trace_csv().unwrap(),
+                   "line,executed,before,after\n1,false,0,0\n2,true,0,1\n3,true,0,10\n4,true,10,-10\n");
+
+        let text = report.to_text(&instructions);
+        assert!(text.contains("     1 hits=0 skips=1  b inc 5 if a > 1\n"));
+        assert!(text.contains("  c     min=-10 (line 4) max=10 (line 3) final=-10\n"));
+        assert!(text.contains("     1 skipped\n     2 a: 0 -> 1\n"));
+    }
+}
@@ -0,0 +1,308 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read};
+
+pub const CHUNK_SIZE: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Normal,
+    Garbage,
+}
+
+enum Action {
+    LvlUp,
+    LvlDown,
+    ChgMode(Mode),
+    Escape,
+    Ignore,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Totals {
+    pub score: u32,
+    pub garbage: u32,
+}
+
+#[derive(Debug)]
+pub enum ErrorKind {
+    UnexpectedChar(u8),
+    UnmatchedClose,
+    UnclosedGroup,
+    UnterminatedGarbage,
+    DanglingEscape,
+    UnexpectedEof,
+    Io(io::Error),
+}
+
+/// Error at the given byte offset, with the state of the scanner at that point
+#[derive(Debug)]
+pub struct ScanError {
+    pub kind: ErrorKind,
+    pub offset: u64,
+    pub depth: u32,
+    pub mode: Mode,
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            ErrorKind::UnexpectedChar(ch) => write!(f, "unexpected char {:?}", *ch as char)?,
+            ErrorKind::UnmatchedClose => write!(f, "'}}' without matching '{{'")?,
+            ErrorKind::UnclosedGroup => write!(f, "unclosed group")?,
+            ErrorKind::UnterminatedGarbage => write!(f, "unterminated garbage")?,
+            ErrorKind::DanglingEscape => write!(f, "'!' at end of stream")?,
+            ErrorKind::UnexpectedEof => write!(f, "unexpected end of stream")?,
+            ErrorKind::Io(e) => write!(f, "read error: {}", e)?,
+        }
+        write!(f, " at byte {} (depth {}, {:?} mode)", self.offset, self.depth, self.mode)
+    }
+}
+
+impl Error for ScanError {}
+
+/// Incremental scanner: the stream can be fed in chunks of any size, the state
+/// is kept between them.
+/// In recovery mode, errors are collected instead of returned and the scanner
+/// continues, ignoring the wrong chars and closing everything left open at the
+/// end, to get a best-effort result.
+pub struct Scanner {
+    mode: Mode,
+    depth: u32,
+    skip_next: bool,
+    offset: u64,
+    totals: Totals,
+    open_groups: Vec<u64>, // offsets of the '{' not closed yet
+    garbage_start: u64,
+    diagnostics: Option<Vec<ScanError>>,
+}
+
+impl Scanner {
+    pub fn new() -> Scanner {
+        Scanner {
+            mode: Mode::Normal, depth: 0, skip_next: false, offset: 0, totals: Totals::default(),
+            open_groups: Vec::new(), garbage_start: 0, diagnostics: None,
+        }
+    }
+
+    pub fn recovering() -> Scanner {
+        Scanner { diagnostics: Some(Vec::new()), ..Scanner::new() }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<(), ScanError> {
+        for &ch in chunk {
+            self.scan_byte(ch)?;
+            self.offset += 1;
+        }
+        Ok(())
+    }
+
+    pub fn finish(&mut self) -> Result<Totals, ScanError> {
+        if self.skip_next {
+            let error = ScanError { offset: self.offset - 1, ..self.error(ErrorKind::DanglingEscape) };
+            self.report(error)?;
+        }
+        if self.mode == Mode::Garbage {
+            let error = ScanError { offset: self.garbage_start, ..self.error(ErrorKind::UnterminatedGarbage) };
+            self.report(error)?;
+            self.mode = Mode::Normal;
+        }
+        while let Some(offset) = self.open_groups.pop() {
+            let error = ScanError { offset, ..self.error(ErrorKind::UnclosedGroup) };
+            self.report(error)?;
+            self.totals.score += self.depth;
+            self.depth -= 1;
+        }
+
+        if let Some(diagnostics) = &mut self.diagnostics {
+            diagnostics.sort_by_key(|e| e.offset);
+        }
+        Ok(self.totals)
+    }
+
+    fn scan_byte(&mut self, ch: u8) -> Result<(), ScanError> {
+        if self.skip_next {
+            self.skip_next = false;
+            return Ok(());
+        }
+
+        let action = match self.mode {
+            Mode::Normal => parse_ch_mode_normal(ch),
+            Mode::Garbage => parse_ch_mode_garbage(ch),
+        };
+
+        match action {
+            Some(Action::LvlUp) => {self.depth += 1; self.open_groups.push(self.offset);},
+            Some(Action::LvlDown) if self.depth == 0 => self.report(self.error(ErrorKind::UnmatchedClose))?,
+            Some(Action::LvlDown) => {self.totals.score += self.depth; self.depth -= 1; self.open_groups.pop();},
+            Some(Action::ChgMode(m)) => {self.mode = m; self.garbage_start = self.offset;},
+            Some(Action::Escape) => {self.skip_next = true;},
+            // count chars, not bytes: skip UTF-8 continuation bytes
+            Some(Action::Ignore) => if self.mode == Mode::Garbage && ch & 0xc0 != 0x80 {
+                self.totals.garbage += 1;
+            },
+            None => self.report(self.error(ErrorKind::UnexpectedChar(ch)))?,
+        }
+        Ok(())
+    }
+
+    /// Save the error if recovering, return it otherwise
+    fn report(&mut self, error: ScanError) -> Result<(), ScanError> {
+        match &mut self.diagnostics {
+            Some(diagnostics) => {
+                diagnostics.push(error);
+                Ok(())
+            },
+            None => Err(error),
+        }
+    }
+
+    fn error(&self, kind: ErrorKind) -> ScanError {
+        ScanError { kind, offset: self.offset, depth: self.depth, mode: self.mode }
+    }
+}
+
+/// Scan the stream reading it in chunks of CHUNK_SIZE bytes
+pub fn scan<R: Read>(reader: R) -> Result<Totals, ScanError> {
+    scan_with(Scanner::new(), reader).map(|(totals, _)| totals)
+}
+
+/// Scan the stream in recovery mode: only read errors are returned as errors,
+/// the rest are returned along with the best-effort totals
+pub fn scan_recovering<R: Read>(reader: R) -> Result<(Totals, Vec<ScanError>), ScanError> {
+    scan_with(Scanner::recovering(), reader)
+}
+
+fn scan_with<R: Read>(mut scanner: Scanner, mut reader: R) -> Result<(Totals, Vec<ScanError>), ScanError> {
+    let mut buf = [0u8; CHUNK_SIZE];
+
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => scanner.feed(&buf[..n])?,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(scanner.error(ErrorKind::Io(e))),
+        }
+    }
+
+    let totals = scanner.finish()?;
+    Ok((totals, scanner.diagnostics.unwrap_or_default()))
+}
+
+fn parse_ch_mode_normal(ch: u8) -> Option<Action> {
+    match ch {
+        b'{' => Some(Action::LvlUp),
+        b'}' => Some(Action::LvlDown),
+        b'<' => Some(Action::ChgMode(Mode::Garbage)),
+        b',' => Some(Action::Ignore),
+        _ => None,
+    }
+}
+
+fn parse_ch_mode_garbage(ch: u8) -> Option<Action> {
+    match ch {
+        b'>' => Some(Action::ChgMode(Mode::Normal)),
+        b'!' => Some(Action::Escape),
+        _ => Some(Action::Ignore),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn scan_str(input: &str) -> Totals {
+        scan(input.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn scores() {
+        assert_eq!(scan_str("{}").score, 1);
+        assert_eq!(scan_str("{{{}}}").score, 6);
+        assert_eq!(scan_str("{{},{}}").score, 5);
+        assert_eq!(scan_str("{{{},{},{{}}}}").score, 16);
+        assert_eq!(scan_str("{<a>,<a>,<a>,<a>}").score, 1);
+        assert_eq!(scan_str("{{<ab>},{<ab>},{<ab>},{<ab>}}").score, 9);
+        assert_eq!(scan_str("{{<!!>},{<!!>},{<!!>},{<!!>}}").score, 9);
+        assert_eq!(scan_str("{{<a!>},{<a!>},{<a!>},{<ab>}}").score, 3);
+    }
+
+    #[test]
+    fn garbage() {
+        assert_eq!(scan_str("{<>}").garbage, 0);
+        assert_eq!(scan_str("{<random characters>}").garbage, 17);
+        assert_eq!(scan_str("{<<<<>}").garbage, 3);
+        assert_eq!(scan_str("{<{!>}>}").garbage, 2);
+        assert_eq!(scan_str("{<!!>}").garbage, 0);
+        assert_eq!(scan_str("{<!!!>>}").garbage, 0);
+        assert_eq!(scan_str("{<{o\"i!a,<{i<a>}").garbage, 10);
+        assert_eq!(scan_str("{<ñ!ña>}").garbage, 2);
+    }
+
+    #[test]
+    fn chunk_boundaries() {
+        let input = "{{<!!>},{<a!>},{<!>!!>},{<ab>}}".as_bytes();
+        let expects = scan(input).unwrap();
+
+        for chunk_size in 1..input.len() {
+            let mut scanner = Scanner::new();
+            for chunk in input.chunks(chunk_size) {
+                scanner.feed(chunk).unwrap();
+            }
+            assert_eq!(scanner.finish().unwrap(), expects, "chunk size {}", chunk_size);
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let error = scan("{{<a>},x}".as_bytes()).unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::UnexpectedChar(b'x')));
+        assert_eq!((error.offset, error.depth, error.mode), (7, 1, Mode::Normal));
+        assert_eq!(error.to_string(), "unexpected char 'x' at byte 7 (depth 1, Normal mode)");
+
+        let error = scan("{}}".as_bytes()).unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::UnmatchedClose));
+        assert_eq!((error.offset, error.depth), (2, 0));
+
+        let error = scan("{<a!".as_bytes()).unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::DanglingEscape));
+        assert_eq!((error.offset, error.depth, error.mode), (3, 1, Mode::Garbage));
+
+        let error = scan("{{<a".as_bytes()).unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::UnterminatedGarbage));
+        assert_eq!((error.offset, error.depth, error.mode), (2, 2, Mode::Garbage));
+
+        let error = scan("{{},{".as_bytes()).unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::UnclosedGroup));
+        assert_eq!((error.offset, error.depth), (4, 2));
+    }
+
+    #[test]
+    fn recovery() {
+        let (totals, diagnostics) = scan_recovering("{}},{x,{<a>}".as_bytes()).unwrap();
+        assert_eq!(totals, Totals { score: 1 + 1 + 2, garbage: 1 });
+        assert_eq!(diagnostics.len(), 3);
+        assert!(matches!(diagnostics[0].kind, ErrorKind::UnmatchedClose));
+        assert!(matches!(diagnostics[1].kind, ErrorKind::UnclosedGroup));
+        assert_eq!((diagnostics[1].offset, diagnostics[1].depth), (4, 1));
+        assert!(matches!(diagnostics[2].kind, ErrorKind::UnexpectedChar(b'x')));
+        assert_eq!((diagnostics[2].offset, diagnostics[2].depth), (5, 1));
+
+        let (totals, diagnostics) = scan_recovering("{{<ab},{<!!>}},{{<a!".as_bytes()).unwrap();
+        assert_eq!(totals, Totals { score: 2 + 1 + 2 + 1, garbage: 7 });
+        let found: Vec<(u64, u32, String)> = diagnostics.iter()
+            .map(|e| (e.offset, e.depth, format!("{:?}", e.kind)))
+            .collect();
+        assert_eq!(found, vec![
+            (15, 1, String::from("UnclosedGroup")),
+            (16, 2, String::from("UnclosedGroup")),
+            (17, 2, String::from("UnterminatedGarbage")),
+            (19, 2, String::from("DanglingEscape")),
+        ]);
+        assert_eq!(diagnostics[3].to_string(), "'!' at end of stream at byte 19 (depth 2, Garbage mode)");
+
+        let (totals, diagnostics) = scan_recovering("{{<!!>},{<!!>}}".as_bytes()).unwrap();
+        assert_eq!(totals.score, 5);
+        assert!(diagnostics.is_empty());
+    }
+}
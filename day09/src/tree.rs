@@ -0,0 +1,306 @@
+use std::fmt::{self, Write};
+use std::ops::Range;
+use super::stream::{ErrorKind, Mode, ScanError};
+
+/// Syntax tree of a stream. Spans are the byte ranges in the original input,
+/// and garbage keeps its raw content, including the '!' escapes.
+#[derive(Debug, PartialEq)]
+pub enum Node {
+    Group { children: Vec<Node>, depth: u32, span: Range<usize> },
+    Garbage { raw: String, cancelled_count: u32, span: Range<usize> },
+}
+
+impl Node {
+    /// Score of this group alone, or 0 for garbage
+    pub fn score(&self) -> u32 {
+        match self {
+            Node::Group { depth, .. } => *depth,
+            Node::Garbage { .. } => 0,
+        }
+    }
+
+    pub fn total_score(&self) -> u32 {
+        self.descendants().map(|node| node.score()).sum()
+    }
+
+    /// Non-cancelled garbage chars, including the nested groups' ones
+    pub fn garbage_total(&self) -> u32 {
+        self.descendants()
+            .map(|node| match node {
+                Node::Group { .. } => 0,
+                Node::Garbage { raw, cancelled_count, .. } => raw.chars().count() as u32 - 2 * cancelled_count,
+            })
+            .sum()
+    }
+
+    /// The node and all the nodes inside it, iterative so deep nesting can't
+    /// overflow the stack
+    fn descendants(&self) -> impl Iterator<Item = &Node> {
+        let mut pending = vec![self];
+        std::iter::from_fn(move || {
+            let node = pending.pop()?;
+            if let Node::Group { children, .. } = node {
+                pending.extend(children);
+            }
+            Some(node)
+        })
+    }
+
+    /// Indented representation, with the stats of each group
+    pub fn pretty(&self) -> String {
+        let mut out = String::new();
+        // (node, indent), or None for the closing brace of a group
+        let mut pending = vec![(Some(self), 0)];
+
+        while let Some((node, indent)) = pending.pop() {
+            let pad = "  ".repeat(indent);
+            match node {
+                Some(node @ Node::Group { children, depth, span }) => {
+                    writeln!(out, "{}{{  # depth={} score={} garbage={} bytes={}..{}", pad, depth, node.score(),
+                             node.garbage_total(), span.start, span.end).unwrap();
+                    pending.push((None, indent));
+                    pending.extend(children.iter().rev().map(|child| (Some(child), indent + 1)));
+                },
+                Some(Node::Garbage { raw, cancelled_count, span }) => {
+                    writeln!(out, "{}<{}>  # cancelled={} bytes={}..{}", pad, raw, cancelled_count,
+                             span.start, span.end).unwrap();
+                },
+                None => writeln!(out, "{}}}", pad).unwrap(),
+            }
+        }
+        out
+    }
+}
+
+/// Serialize back to the exact original input
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // nodes still to write, or the text between them
+        let mut pending = vec![Ok(self)];
+
+        while let Some(item) = pending.pop() {
+            match item {
+                Ok(Node::Group { children, .. }) => {
+                    write!(f, "{{")?;
+                    pending.push(Err("}"));
+                    for (i, child) in children.iter().enumerate().rev() {
+                        pending.push(Ok(child));
+                        if i > 0 {
+                            pending.push(Err(","));
+                        }
+                    }
+                },
+                Ok(Node::Garbage { raw, .. }) => write!(f, "<{}>", raw)?,
+                Err(text) => write!(f, "{}", text)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// Each "{" of the stream nests a Group one level deeper, and the default drop
+// recurses once per level, so the children are flattened into a list instead
+impl Drop for Node {
+    fn drop(&mut self) {
+        if let Node::Group { children, .. } = self {
+            let mut pending = std::mem::take(children);
+            while let Some(mut node) = pending.pop() {
+                if let Node::Group { children, .. } = &mut node {
+                    pending.append(children);
+                }
+            }
+        }
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    depth: u32,
+}
+
+/// Iterative parser, with the groups still open in a stack
+pub fn parse(input: &str) -> Result<Node, ScanError> {
+    let mut parser = Parser { input, pos: 0, depth: 0 };
+    // (start, children) of each open group
+    let mut open: Vec<(usize, Vec<Node>)> = Vec::new();
+
+    loop {
+        // a node is expected: at the start, or after '{' or ','
+        let mut node = match parser.peek() {
+            Some(b'{') => {
+                open.push((parser.pos, Vec::new()));
+                parser.pos += 1;
+                parser.depth += 1;
+                if parser.peek() != Some(b'}') {
+                    continue;
+                }
+                parser.close_group(&mut open)
+            },
+            Some(b'<') => parser.parse_garbage()?,
+            Some(ch) => return Err(parser.error(ErrorKind::UnexpectedChar(ch), Mode::Normal)),
+            None => return Err(match open.last() {
+                Some(&(start, _)) => parser.unclosed(start),
+                None => parser.error(ErrorKind::UnexpectedEof, Mode::Normal),
+            }),
+        };
+
+        // the node is complete, add it to its group and close the groups that end after it
+        loop {
+            let (start, children) = match open.last_mut() {
+                Some(group) => group,
+                None => {
+                    return match parser.peek() {
+                        Some(ch) => Err(parser.error(ErrorKind::UnexpectedChar(ch), Mode::Normal)),
+                        None => Ok(node),
+                    };
+                },
+            };
+            let start = *start;
+            children.push(node);
+            match parser.peek() {
+                Some(b',') => {
+                    parser.pos += 1;
+                    break;
+                },
+                Some(b'}') => node = parser.close_group(&mut open),
+                Some(ch) => return Err(parser.error(ErrorKind::UnexpectedChar(ch), Mode::Normal)),
+                None => return Err(parser.unclosed(start)),
+            }
+        }
+    }
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.pos).copied()
+    }
+
+    fn error(&self, kind: ErrorKind, mode: Mode) -> ScanError {
+        ScanError { kind, offset: self.pos as u64, depth: self.depth, mode }
+    }
+
+    fn unclosed(&self, start: usize) -> ScanError {
+        ScanError { offset: start as u64, ..self.error(ErrorKind::UnclosedGroup, Mode::Normal) }
+    }
+
+    /// Consume the '}' of the innermost open group and build it
+    fn close_group(&mut self, open: &mut Vec<(usize, Vec<Node>)>) -> Node {
+        let (start, children) = open.pop().unwrap();
+        self.pos += 1;
+        let depth = self.depth;
+        self.depth -= 1;
+        Node::Group { children, depth, span: start..self.pos }
+    }
+
+    fn parse_garbage(&mut self) -> Result<Node, ScanError> {
+        let start = self.pos;
+        let mut cancelled_count = 0;
+        self.pos += 1;
+
+        loop {
+            match self.peek() {
+                Some(b'>') => break,
+                Some(b'!') if self.pos + 1 < self.input.len() => {
+                    self.pos += 2;
+                    cancelled_count += 1;
+                },
+                Some(b'!') => return Err(self.error(ErrorKind::DanglingEscape, Mode::Garbage)),
+                Some(_) => self.pos += 1,
+                None => {
+                    let error = self.error(ErrorKind::UnterminatedGarbage, Mode::Garbage);
+                    return Err(ScanError { offset: start as u64, ..error });
+                },
+            }
+        }
+
+        self.pos += 1;
+        let raw = String::from(&self.input[start + 1..self.pos - 1]);
+        Ok(Node::Garbage { raw, cancelled_count, span: start..self.pos })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::stream::scan;
+
+    #[test]
+    fn tree() {
+        let node = parse("{{<a!>b>},{},<>}").unwrap();
+        assert_eq!(node, Node::Group { depth: 1, span: 0..16, children: vec![
+            Node::Group { depth: 2, span: 1..9, children: vec![
+                Node::Garbage { raw: String::from("a!>b"), cancelled_count: 1, span: 2..8 },
+            ]},
+            Node::Group { depth: 2, span: 10..12, children: vec![] },
+            Node::Garbage { raw: String::new(), cancelled_count: 0, span: 13..15 },
+        ]});
+        assert_eq!((node.score(), node.total_score(), node.garbage_total()), (1, 5, 2));
+    }
+
+    #[test]
+    fn round_trip() {
+        for input in ["{}", "<{o\"i!a,<{i<a>", "{{<!!>},{<!!>},{<!!>},{<!!>}}", "{{<a!>},{<a!>},{<a!>},{<ab>}}",
+                      "{{{},{},{{}}}}", "{<ñ!ñ>,{<!>>}}"].iter() {
+            assert_eq!(parse(input).unwrap().to_string(), *input);
+        }
+
+        let input = std::fs::read_to_string("input.txt").unwrap();
+        let node = parse(&input).unwrap();
+        assert_eq!(node.to_string(), input);
+
+        let totals = scan(input.as_bytes()).unwrap();
+        assert_eq!((node.total_score(), node.garbage_total()), (totals.score, totals.garbage));
+    }
+
+    #[test]
+    fn pretty_print() {
+        assert_eq!(parse("{{},<a!!>}").unwrap().pretty(), concat!(
+            "{  # depth=1 score=1 garbage=1 bytes=0..10\n",
+            "  {  # depth=2 score=2 garbage=0 bytes=1..3\n",
+            "  }\n",
+            "  <a!!>  # cancelled=1 bytes=4..9\n",
+            "}\n"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = parse("{{},}").unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::UnexpectedChar(b'}')));
+        assert_eq!((error.offset, error.depth), (4, 1));
+
+        let error = parse("{<ab!").unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::DanglingEscape));
+        assert_eq!((error.offset, error.mode), (4, Mode::Garbage));
+
+        let error = parse("{{<ab>},<a").unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::UnterminatedGarbage));
+        assert_eq!((error.offset, error.depth), (8, 1));
+
+        let error = parse("{{},{").unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::UnclosedGroup));
+        assert_eq!((error.offset, error.depth), (4, 2));
+
+        let error = parse("").unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::UnexpectedEof));
+
+        let error = parse("{}{}").unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::UnexpectedChar(b'{')));
+        assert_eq!((error.offset, error.depth), (2, 0));
+
+        let error = parse("{<a>}>").unwrap_err();
+        assert!(matches!(error.kind, ErrorKind::UnexpectedChar(b'>')));
+        assert_eq!((error.offset, error.depth), (5, 0));
+    }
+
+    #[test]
+    fn deep_tree() {
+        // as deep as possible while the total score fits in a u32
+        let depth = 90_000;
+        let input = format!("{}<a>{}", "{".repeat(depth), "}".repeat(depth));
+        let node = parse(&input).unwrap();
+        let totals = scan(input.as_bytes()).unwrap();
+        assert_eq!((node.total_score(), node.garbage_total()), (totals.score, totals.garbage));
+        assert_eq!(node.to_string(), input);
+    }
+}
@@ -0,0 +1,184 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Read};
+use std::process;
+use day10::*;
+
+const USAGE: &str = "Usage: knothash [--lines] [FILE]...
+       knothash --check [--strict] [FILE]...
+Print the knot hash of each FILE, or of the standard input when FILE is - or missing.
+  --lines   hash each line of the input separately, named FILE:LINE (these
+            names aren't files, so --check can't read this output back)
+  --check   read HASH  FILE lines from the FILEs and check the hashes
+  --strict  with --check, fail on improperly formatted lines instead of
+            only warning about them";
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut lines = false;
+    let mut check = false;
+    let mut strict = false;
+    let mut filenames = Vec::new();
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--lines" => lines = true,
+            "--check" => check = true,
+            "--strict" => strict = true,
+            "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            },
+            _ if arg.starts_with("--") => {
+                eprintln!("knothash: unknown option {}\n{}", arg, USAGE);
+                process::exit(2);
+            },
+            _ => filenames.push(arg),
+        }
+    }
+    if lines && check {
+        eprintln!("knothash: --lines can't be used with --check");
+        process::exit(2);
+    }
+    if strict && !check {
+        eprintln!("knothash: --strict is only meaningful with --check");
+        process::exit(2);
+    }
+    if filenames.is_empty() {
+        filenames.push(String::from("-"));
+    }
+
+    let mut ok = true;
+    for filename in &filenames {
+        let result = if check { check_file(filename, strict) } else { print_hashes(filename, lines) };
+        match result {
+            Ok(file_ok) => ok &= file_ok,
+            Err(e) => {
+                eprintln!("knothash: {}: {}", filename, e);
+                ok = false;
+            },
+        }
+    }
+
+    if !ok {
+        process::exit(1);
+    }
+    Ok(())
+}
+
+fn print_hashes(filename: &str, lines: bool) -> io::Result<bool> {
+    let input = read_input(filename)?;
+    if lines {
+        for (i, line) in split_lines(&input).enumerate() {
+            println!("{}  {}:{}", hash(line), filename, i + 1);
+        }
+    } else {
+        println!("{}  {}", hash(&input), filename);
+    }
+    Ok(true)
+}
+
+/// Check the hashes listed in the checksum file, returning whether all match.
+/// Like md5sum, improperly formatted lines are only a failure if strict, or if
+/// there are no properly formatted ones.
+fn check_file(filename: &str, strict: bool) -> io::Result<bool> {
+    let input = read_input(filename)?;
+    let (mut matched, mut failed, mut unreadable, mut malformed) = (0, 0, 0, 0);
+
+    for line in split_lines(&input) {
+        let (expects, name) = match parse_checksum_line(&String::from_utf8_lossy(line)) {
+            Some(entry) => entry,
+            None => {
+                malformed += 1;
+                continue;
+            },
+        };
+        match read_input(&name) {
+            Ok(input) if hash(&input) == expects => {
+                println!("{}: OK", name);
+                matched += 1;
+            },
+            Ok(_) => {
+                println!("{}: FAILED", name);
+                failed += 1;
+            },
+            Err(e) => {
+                eprintln!("knothash: {}: {}", name, e);
+                println!("{}: FAILED open or read", name);
+                unreadable += 1;
+            },
+        }
+    }
+
+    if failed + unreadable + matched == 0 {
+        eprintln!("knothash: {}: no properly formatted checksum lines found", filename);
+        return Ok(false);
+    }
+    if malformed > 0 {
+        eprintln!("knothash: WARNING: {} line(s) improperly formatted", malformed);
+    }
+    if unreadable > 0 {
+        eprintln!("knothash: WARNING: {} listed file(s) could not be read", unreadable);
+    }
+    if failed > 0 {
+        eprintln!("knothash: WARNING: {} computed checksum(s) did NOT match", failed);
+    }
+    Ok(failed + unreadable == 0 && (malformed == 0 || !strict))
+}
+
+fn read_input(filename: &str) -> io::Result<Vec<u8>> {
+    if filename == "-" {
+        let mut input = Vec::new();
+        io::stdin().read_to_end(&mut input)?;
+        Ok(input)
+    } else {
+        fs::read(filename)
+    }
+}
+
+fn hash(input: &[u8]) -> String {
+    to_hex(&knot_hash(input))
+}
+
+/// Lines without their line ending, \n or \r\n
+fn split_lines(input: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let empty = input.is_empty();
+    let input = input.strip_suffix(b"\n").unwrap_or(input);
+    input.split(|&b| b == b'\n')
+        .filter(move |_| !empty)
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+}
+
+/// Parse "HASH  FILENAME", also accepting the "HASH *FILENAME" binary mark of md5sum
+fn parse_checksum_line(line: &str) -> Option<(String, String)> {
+    let (hash, name) = line.split_at(line.find(' ')?);
+    let name = name.strip_prefix("  ").or_else(|| name.strip_prefix(" *"))?;
+    if hash.len() != 32 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) || name.is_empty() {
+        return None;
+    }
+    Some((hash.to_ascii_lowercase(), String::from(name)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lines() {
+        let lines: Vec<&[u8]> = split_lines(b"AoC 2017\r\n\n1,2,3\n").collect();
+        assert_eq!(lines, vec![&b"AoC 2017"[..], b"", b"1,2,3"]);
+        assert_eq!(split_lines(b"").count(), 0);
+        assert_eq!(split_lines(b"\n").count(), 1);
+        assert_eq!(hash(lines[0]), "33efeb34ea91902bb2f59c9920caa6cd");
+    }
+
+    #[test]
+    fn checksum_lines() {
+        assert_eq!(parse_checksum_line("33EFEB34EA91902BB2F59C9920CAA6CD  my file.txt"),
+                   Some((String::from("33efeb34ea91902bb2f59c9920caa6cd"), String::from("my file.txt"))));
+        assert_eq!(parse_checksum_line("33efeb34ea91902bb2f59c9920caa6cd *-"),
+                   Some((String::from("33efeb34ea91902bb2f59c9920caa6cd"), String::from("-"))));
+        assert_eq!(parse_checksum_line("33efeb34ea91902bb2f59c9920caa6cd file"), None);
+        assert_eq!(parse_checksum_line("33efeb34  file"), None);
+        assert_eq!(parse_checksum_line("no hash here"), None);
+    }
+}
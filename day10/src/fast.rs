@@ -0,0 +1,90 @@
+use std::thread;
+use super::{ROUNDS, SUFFIX};
+
+/// Standard knot hash on a byte array. Instead of moving the current position
+/// around the list, the list is rotated to keep it at index 0, so reversals
+/// never wrap around the end of the list.
+pub fn knot_hash(input: &[u8]) -> [u8; 16] {
+    let mut list = [0u8; 256];
+    for (i, elem) in list.iter_mut().enumerate() {
+        *elem = i as u8;
+    }
+
+    let suffix: Vec<u8> = SUFFIX.iter().map(|&len| len as u8).collect();
+    let mut pos = 0;
+    let mut skip = 0;
+    for _ in 0..ROUNDS {
+        for &len in input.iter().chain(&suffix) {
+            list[..len as usize].reverse();
+            let step = (len as usize + skip) % list.len();
+            list.rotate_left(step);
+            pos = (pos + step) % list.len();
+            skip += 1;
+        }
+    }
+    // back to the original positions
+    list.rotate_right(pos);
+
+    let mut hash = [0u8; 16];
+    for (byte, block) in hash.iter_mut().zip(list.chunks(16)) {
+        *byte = block.iter().fold(0, |acc, &elem| acc ^ elem);
+    }
+    hash
+}
+
+/// Knot hashes of all the inputs, in the same order, split across threads
+pub fn knot_hash_many<T: AsRef<[u8]> + Sync>(inputs: &[T]) -> Vec<[u8; 16]> {
+    let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let chunk_size = inputs.len().div_ceil(threads).max(1);
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = inputs.chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || {
+                chunk.iter().map(|input| knot_hash(input.as_ref())).collect::<Vec<_>>()
+            }))
+            .collect();
+        handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{apply_dense_knot_tie_hash, bytes_to_lens, to_hex};
+
+    fn slow_hash(input: &[u8]) -> String {
+        apply_dense_knot_tie_hash((0..256).collect(), &bytes_to_lens(input))
+    }
+
+    fn test_inputs() -> Vec<Vec<u8>> {
+        // text, bytes that aren't valid UTF-8, and every byte value
+        let mut inputs: Vec<Vec<u8>> = [
+            &b""[..], b"a", b"flqrgnkx-0", b"flqrgnkx-127", b"\x00", b"\x00\x80\xff", b"\xc3\xa9t\xc3\xa9",
+            b"0123456789abcde", b"0123456789abcdef", b"0123456789abcdefg",
+        ].iter().map(|input| input.to_vec()).collect();
+        inputs.push((0..=255).collect());
+        inputs.push((0..=255).rev().cycle().take(1000).collect());
+        inputs
+    }
+
+    #[test]
+    fn same_as_slow_hash() {
+        assert_eq!(to_hex(&knot_hash(b"")), "a2582a3a0e66e6e86e3812dcb672a272");
+        assert_eq!(to_hex(&knot_hash(b"AoC 2017")), "33efeb34ea91902bb2f59c9920caa6cd");
+        assert_eq!(to_hex(&knot_hash(b"1,2,3")), "3efbe78a8d82f29979031a4aa0b16a9d");
+        assert_eq!(to_hex(&knot_hash(b"1,2,4")), "63960835bcdc130f0b66d7ff4f6a5a8e");
+
+        for input in test_inputs() {
+            assert_eq!(to_hex(&knot_hash(&input)), slow_hash(&input), "input {:?}", input);
+        }
+    }
+
+    #[test]
+    fn parallel_hashing() {
+        let inputs = test_inputs();
+        let expects: Vec<[u8; 16]> = inputs.iter().map(|input| knot_hash(input)).collect();
+        assert_eq!(knot_hash_many(&inputs), expects);
+        assert_eq!(knot_hash_many(&inputs[..1]), &expects[..1]);
+        assert!(knot_hash_many::<&str>(&[]).is_empty());
+    }
+}
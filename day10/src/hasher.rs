@@ -0,0 +1,217 @@
+use std::error::Error;
+use std::fmt;
+use std::hash::Hasher;
+use super::{LIST_SIZE, ROUNDS, BLOCK_SIZE, SUFFIX, apply_knot_tie_hash, densify, knot_hash};
+
+#[derive(Debug, PartialEq)]
+pub enum KnotHashError {
+    ListSize(usize),
+    NoRounds,
+    BlockSize { block_size: usize, list_size: usize },
+    LengthTooLong { len: usize, list_size: usize },
+}
+
+impl fmt::Display for KnotHashError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KnotHashError::ListSize(size) =>
+                write!(f, "list size {} out of range 1-{}", size, LIST_SIZE),
+            KnotHashError::NoRounds => write!(f, "at least 1 round is needed"),
+            KnotHashError::BlockSize { block_size, list_size } =>
+                write!(f, "block size {} doesn't divide list size {}", block_size, list_size),
+            KnotHashError::LengthTooLong { len, list_size } =>
+                write!(f, "length {} longer than list size {}", len, list_size),
+        }
+    }
+}
+
+impl Error for KnotHashError {}
+
+/// Builder of knot hash variants. Defaults to the standard knot hash.
+/// List elements must fit in a byte, so lists can't be longer than 256.
+#[derive(Debug, Clone)]
+pub struct KnotHashBuilder {
+    list_size: usize,
+    rounds: usize,
+    block_size: usize,
+    suffix: Vec<usize>,
+}
+
+impl Default for KnotHashBuilder {
+    fn default() -> Self {
+        KnotHashBuilder::new()
+    }
+}
+
+impl KnotHashBuilder {
+    pub fn new() -> KnotHashBuilder {
+        KnotHashBuilder {
+            list_size: LIST_SIZE, rounds: ROUNDS, block_size: BLOCK_SIZE, suffix: SUFFIX.to_vec()
+        }
+    }
+
+    pub fn list_size(mut self, list_size: usize) -> Self {
+        self.list_size = list_size;
+        self
+    }
+
+    pub fn rounds(mut self, rounds: usize) -> Self {
+        self.rounds = rounds;
+        self
+    }
+
+    pub fn block_size(mut self, block_size: usize) -> Self {
+        self.block_size = block_size;
+        self
+    }
+
+    pub fn suffix(mut self, suffix: &[usize]) -> Self {
+        self.suffix = suffix.to_vec();
+        self
+    }
+
+    pub fn build(self) -> Result<KnotHashVariant, KnotHashError> {
+        if self.list_size == 0 || self.list_size > LIST_SIZE {
+            return Err(KnotHashError::ListSize(self.list_size));
+        }
+        if self.rounds == 0 {
+            return Err(KnotHashError::NoRounds);
+        }
+        if !self.list_size.is_multiple_of(self.block_size) {
+            return Err(KnotHashError::BlockSize { block_size: self.block_size, list_size: self.list_size });
+        }
+        if let Some(&len) = self.suffix.iter().find(|&&len| len > self.list_size) {
+            return Err(KnotHashError::LengthTooLong { len, list_size: self.list_size });
+        }
+        Ok(KnotHashVariant { params: self, input: Vec::new() })
+    }
+}
+
+/// Knot hash with custom parameters, the hash is list_size / block_size bytes
+#[derive(Debug, Clone)]
+pub struct KnotHashVariant {
+    params: KnotHashBuilder,
+    input: Vec<usize>,
+}
+
+impl KnotHashVariant {
+    /// Fails if a byte is longer than the list, only possible for lists under 255
+    pub fn update(&mut self, bytes: &[u8]) -> Result<(), KnotHashError> {
+        let list_size = self.params.list_size;
+        if let Some(&len) = bytes.iter().find(|&&b| b as usize > list_size) {
+            return Err(KnotHashError::LengthTooLong { len: len as usize, list_size });
+        }
+        self.input.extend(bytes.iter().map(|&b| b as usize));
+        Ok(())
+    }
+
+    pub fn finalize(&self) -> Vec<u8> {
+        let mut lens = self.input.clone();
+        lens.extend(&self.params.suffix);
+
+        let mut list: Vec<u32> = (0..self.params.list_size as u32).collect();
+        apply_knot_tie_hash(&mut list, &lens, self.params.rounds);
+
+        densify(&list, self.params.block_size).into_iter().map(|elem| elem as u8).collect()
+    }
+}
+
+/// Knot hash with an API like other digests: feed the input with update, in
+/// as many parts as wanted, and get the hash with finalize.
+/// As the knot hash needs the whole input for every round, it's buffered.
+#[derive(Debug, Clone, Default)]
+pub struct KnotHash {
+    input: Vec<u8>,
+}
+
+impl KnotHash {
+    pub fn new() -> KnotHash {
+        KnotHash { input: Vec::new() }
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.input.extend_from_slice(bytes);
+    }
+
+    pub fn finalize(&self) -> [u8; 16] {
+        knot_hash(&self.input)
+    }
+}
+
+/// Only the first 8 bytes of the knot hash are used as hash
+impl Hasher for KnotHash {
+    fn write(&mut self, bytes: &[u8]) {
+        self.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        let hash = self.finalize();
+        let mut first_bytes = [0u8; 8];
+        first_bytes.copy_from_slice(&hash[..8]);
+        u64::from_be_bytes(first_bytes)
+    }
+}
+
+pub fn to_hex(hash: &[u8]) -> String {
+    hash.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{apply_dense_knot_tie_hash, input_to_ascii};
+
+    #[test]
+    fn knot_hash() {
+        let mut hasher = KnotHash::new();
+        assert_eq!(to_hex(&hasher.finalize()), "a2582a3a0e66e6e86e3812dcb672a272");
+        hasher.update(b"AoC ");
+        hasher.update(b"2017");
+        assert_eq!(to_hex(&hasher.finalize()), "33efeb34ea91902bb2f59c9920caa6cd");
+
+        for input in ["1,2,3", "1,2,4", "flqrgnkx-0", "xlqgujun-127"].iter() {
+            let mut hasher = KnotHash::new();
+            hasher.update(input.as_bytes());
+            let expects = apply_dense_knot_tie_hash((0..256).collect(), &input_to_ascii(input));
+            assert_eq!(to_hex(&hasher.finalize()), expects);
+        }
+    }
+
+    #[test]
+    fn std_hasher() {
+        let mut hasher = KnotHash::new();
+        hasher.write(b"AoC 2017");
+        assert_eq!(hasher.finish(), 0x33efeb34ea91902b);
+    }
+
+    #[test]
+    fn variants() {
+        let mut hasher = KnotHashBuilder::new().build().unwrap();
+        hasher.update(b"AoC 2017").unwrap();
+        assert_eq!(to_hex(&hasher.finalize()), "33efeb34ea91902bb2f59c9920caa6cd");
+
+        // part 1 example: 1 round without suffix, block size 1 keeps the sparse hash
+        let mut hasher = KnotHashBuilder::new()
+            .list_size(5).rounds(1).block_size(1).suffix(&[]).build().unwrap();
+        hasher.update(&[3, 4, 1, 5]).unwrap();
+        assert_eq!(hasher.finalize(), vec![3, 4, 2, 1, 0]);
+        assert_eq!(hasher.update(b"a"), Err(KnotHashError::LengthTooLong { len: 97, list_size: 5 }));
+
+        let hasher = KnotHashBuilder::new().block_size(32).suffix(&[]).build().unwrap();
+        assert_eq!(hasher.finalize().len(), 8);
+    }
+
+    #[test]
+    fn invalid_variants() {
+        let build = |builder: KnotHashBuilder| builder.build().err();
+        assert_eq!(build(KnotHashBuilder::new().list_size(0)), Some(KnotHashError::ListSize(0)));
+        assert_eq!(build(KnotHashBuilder::new().list_size(257)), Some(KnotHashError::ListSize(257)));
+        assert_eq!(build(KnotHashBuilder::new().rounds(0)), Some(KnotHashError::NoRounds));
+        assert_eq!(build(KnotHashBuilder::new().block_size(0)),
+                   Some(KnotHashError::BlockSize { block_size: 0, list_size: 256 }));
+        assert_eq!(build(KnotHashBuilder::new().list_size(100)),
+                   Some(KnotHashError::BlockSize { block_size: 16, list_size: 100 }));
+        assert_eq!(build(KnotHashBuilder::new().list_size(64).suffix(&[65])),
+                   Some(KnotHashError::LengthTooLong { len: 65, list_size: 64 }));
+    }
+}
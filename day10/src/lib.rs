@@ -1,4 +1,13 @@
+mod fast;
+mod hasher;
+
+pub use fast::{knot_hash, knot_hash_many};
+pub use hasher::{KnotHash, KnotHashBuilder, KnotHashError, KnotHashVariant, to_hex};
+
+const LIST_SIZE: usize = 256;
 const ROUNDS: usize = 64;
+const BLOCK_SIZE: usize = 16;
+const SUFFIX: [usize; 5] = [17, 31, 73, 47, 23];
 
 pub fn input_to_ints(input: &str) -> Vec<usize> {
     input.split(",").map(|s| s.parse::<usize>().unwrap()).collect()
@@ -6,37 +15,39 @@ pub fn input_to_ints(input: &str) -> Vec<usize> {
 
 pub fn input_to_ascii(input: &str) -> Vec<usize> {
     let mut lens: Vec<usize> = input.chars().map(|c| c as usize).collect();
-    lens.extend(vec![17, 31, 73, 47, 23]);
+    lens.extend(&SUFFIX);
+    lens
+}
+
+/// Lengths from the bytes of the input, which may not be text. Same as
+/// input_to_ascii for ASCII input.
+pub fn bytes_to_lens(bytes: &[u8]) -> Vec<usize> {
+    let mut lens: Vec<usize> = bytes.iter().map(|&b| b as usize).collect();
+    lens.extend(&SUFFIX);
     lens
 }
 
 pub fn apply_dense_knot_tie_hash(mut list: Vec<u32>, lens: &[usize]) -> String {
     apply_knot_tie_hash(&mut list, lens, ROUNDS);
 
-    let mut list_out = Vec::with_capacity(16);
-
-    let mut i = 0;
-    while i < list.len() {
-        let mut elem_out = 0;
-        for &elem_in in &list[i..(i+16)] {
-            elem_out ^= elem_in;
-        }
-        list_out.push(elem_out);
-        i += 16;
-    }
-
-    list_out.iter()
+    densify(&list, BLOCK_SIZE).iter()
             .map(|n| format!("{:02x}", n))
             .collect()
 }
 
-pub fn apply_knot_tie_hash(mut list: &mut Vec<u32>, lens: &[usize], mut rounds: usize) {
+fn densify(list: &[u32], block_size: usize) -> Vec<u32> {
+    list.chunks(block_size)
+        .map(|block| block.iter().fold(0, |acc, &elem| acc ^ elem))
+        .collect()
+}
+
+pub fn apply_knot_tie_hash(list: &mut [u32], lens: &[usize], mut rounds: usize) {
     let mut pos = 0;
     let mut skip = 0;
 
     while rounds > 0 {
         for &len in lens {
-            reverse(&mut list, pos, len);
+            reverse(list, pos, len);
             pos = (pos + len + skip) % list.len();
             skip += 1;
         }
@@ -44,7 +55,7 @@ pub fn apply_knot_tie_hash(mut list: &mut Vec<u32>, lens: &[usize], mut rounds:
     }
 }
 
-fn reverse(list: &mut Vec<u32>, pos: usize, len: usize) {
+fn reverse(list: &mut [u32], pos: usize, len: usize) {
     if len == 0 {return;}
 
     let mut i = pos;
@@ -53,9 +64,7 @@ fn reverse(list: &mut Vec<u32>, pos: usize, len: usize) {
     let mut j_idx = j % list.len();
 
     while i < j {
-        let tmp = list[i_idx];
-        list[i_idx] = list[j_idx];
-        list[j_idx] = tmp;
+        list.swap(i_idx, j_idx);
 
         i += 1;
         j -= 1;
@@ -114,6 +123,14 @@ mod tests {
         assert_eq!(len, vec![49,44,50,44,51,17,31,73,47,23]);
     }
 
+    #[test]
+    fn bytes_input_parsing() {
+        assert_eq!(bytes_to_lens(b"1,2,3"), input_to_ascii("1,2,3"));
+        // code points for text, UTF-8 bytes for raw input
+        assert_eq!(input_to_ascii("é"), vec![233,17,31,73,47,23]);
+        assert_eq!(bytes_to_lens("é".as_bytes()), vec![195,169,17,31,73,47,23]);
+    }
+
     #[test]
     fn dense_hash() {
         dense_hash_test("", "a2582a3a0e66e6e86e3812dcb672a272");
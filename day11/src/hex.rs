@@ -0,0 +1,396 @@
+use std::error::Error;
+use std::fmt;
+use std::ops::{Add, Mul, Sub};
+use std::str::FromStr;
+
+/// Position in a hexagonal grid in cube coordinates: 3 axes at 120º, with
+/// q + r + s = 0. Moving to a neighbour changes 2 of them, and the distance
+/// between 2 hexagons is the biggest difference in one axis.
+///
+/// Flat-top hexagons, with q growing to the right and r growing down:
+///
+/// ```text
+///         -r    +s
+///           \   /
+///            ---
+///     -q --/     \-- +q
+///          \     /
+///            ---
+///           /   \
+///         -s    +r
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Cube {
+    pub q: i32,
+    pub r: i32,
+    pub s: i32,
+}
+
+/// Same as Cube, without the redundant s coordinate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Axial {
+    pub q: i32,
+    pub r: i32,
+}
+
+/// Directions of a flat-top grid, clockwise from north
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HexDir { N, NE, SE, S, SW, NW }
+
+#[derive(Debug, PartialEq)]
+pub struct ParseDirError(pub String);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Orientation { FlatTop, PointyTop }
+
+/// Conversion between hexagons and pixels, for hexagons with the given size
+/// (center to corner) and with the origin hexagon centered at origin
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Layout {
+    pub orientation: Orientation,
+    pub size: f64,
+    pub origin: (f64, f64),
+}
+
+impl Cube {
+    pub const ORIGIN: Cube = Cube { q: 0, r: 0, s: 0 };
+
+    /// Hexagon with cube coordinates q, r and s
+    ///
+    /// # Panics
+    ///
+    /// If q + r + s isn't 0
+    pub fn new(q: i32, r: i32, s: i32) -> Cube {
+        assert_eq!(q + r + s, 0, "invalid cube coordinates ({}, {}, {})", q, r, s);
+        Cube { q, r, s }
+    }
+
+    pub fn neighbour(self, dir: HexDir) -> Cube {
+        self + dir.offset()
+    }
+
+    pub fn neighbours(self) -> [Cube; 6] {
+        HexDir::ALL.map(|dir| self.neighbour(dir))
+    }
+
+    /// Distance to the origin
+    pub fn length(self) -> u32 {
+        self.q.unsigned_abs().max(self.r.unsigned_abs()).max(self.s.unsigned_abs())
+    }
+
+    pub fn distance(self, other: Cube) -> u32 {
+        (self - other).length()
+    }
+
+    /// Rotate 60º clockwise around the origin
+    pub fn rotate_cw(self) -> Cube {
+        Cube { q: -self.r, r: -self.s, s: -self.q }
+    }
+
+    /// Rotate 60º counterclockwise around the origin
+    pub fn rotate_ccw(self) -> Cube {
+        Cube { q: -self.s, r: -self.q, s: -self.r }
+    }
+
+    /// Hexagons at the given distance, clockwise from the north one
+    pub fn ring(self, radius: u32) -> Vec<Cube> {
+        if radius == 0 {
+            return vec![self];
+        }
+        let mut ring = Vec::with_capacity(6 * radius as usize);
+        let mut hex = self + HexDir::N.offset() * radius as i32;
+        for &dir in &[HexDir::SE, HexDir::S, HexDir::SW, HexDir::NW, HexDir::N, HexDir::NE] {
+            for _ in 0..radius {
+                ring.push(hex);
+                hex = hex.neighbour(dir);
+            }
+        }
+        ring
+    }
+
+    /// Hexagons up to the given distance, ring by ring from the center
+    pub fn spiral(self, radius: u32) -> Vec<Cube> {
+        (0..=radius).flat_map(|r| self.ring(r)).collect()
+    }
+
+    /// Hexagons crossed by a straight line to other, both ends included
+    pub fn line_to(self, other: Cube) -> Vec<Cube> {
+        let n = self.distance(other);
+        // nudge the ends so points in the edge between 2 hexagons go always to the same side
+        let (a, b) = (self.nudged(1.0), other.nudged(1.0));
+        let lerp = |from: f64, to: f64, t: f64| from + (to - from) * t;
+        (0..=n)
+            .map(|i| {
+                let t = if n == 0 { 0.0 } else { i as f64 / n as f64 };
+                Cube::round(lerp(a.0, b.0, t), lerp(a.1, b.1, t), lerp(a.2, b.2, t))
+            })
+            .collect()
+    }
+
+    fn nudged(self, sign: f64) -> (f64, f64, f64) {
+        (self.q as f64 + sign * 1e-6, self.r as f64 + sign * 2e-6, self.s as f64 - sign * 3e-6)
+    }
+
+    /// Nearest hexagon to fractional cube coordinates
+    pub fn round(q: f64, r: f64, s: f64) -> Cube {
+        let (mut rq, mut rr, mut rs) = (q.round(), r.round(), s.round());
+        let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+        if dq > dr && dq > ds {
+            rq = -rr - rs;
+        } else if dr > ds {
+            rr = -rq - rs;
+        } else {
+            rs = -rq - rr;
+        }
+        Cube { q: rq as i32, r: rr as i32, s: rs as i32 }
+    }
+}
+
+impl Add for Cube {
+    type Output = Cube;
+    fn add(self, other: Cube) -> Cube {
+        Cube { q: self.q + other.q, r: self.r + other.r, s: self.s + other.s }
+    }
+}
+
+impl Sub for Cube {
+    type Output = Cube;
+    fn sub(self, other: Cube) -> Cube {
+        Cube { q: self.q - other.q, r: self.r - other.r, s: self.s - other.s }
+    }
+}
+
+impl Mul<i32> for Cube {
+    type Output = Cube;
+    fn mul(self, k: i32) -> Cube {
+        Cube { q: self.q * k, r: self.r * k, s: self.s * k }
+    }
+}
+
+impl From<Axial> for Cube {
+    fn from(axial: Axial) -> Cube {
+        Cube { q: axial.q, r: axial.r, s: -axial.q - axial.r }
+    }
+}
+
+impl From<Cube> for Axial {
+    fn from(cube: Cube) -> Axial {
+        Axial { q: cube.q, r: cube.r }
+    }
+}
+
+impl fmt::Display for Cube {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {}, {})", self.q, self.r, self.s)
+    }
+}
+
+impl Axial {
+    pub fn new(q: i32, r: i32) -> Axial {
+        Axial { q, r }
+    }
+}
+
+impl HexDir {
+    pub const ALL: [HexDir; 6] = [HexDir::N, HexDir::NE, HexDir::SE, HexDir::S, HexDir::SW, HexDir::NW];
+
+    pub fn offset(self) -> Cube {
+        match self {
+            HexDir::N  => Cube { q:  0, r: -1, s:  1 },
+            HexDir::NE => Cube { q:  1, r: -1, s:  0 },
+            HexDir::SE => Cube { q:  1, r:  0, s: -1 },
+            HexDir::S  => Cube { q:  0, r:  1, s: -1 },
+            HexDir::SW => Cube { q: -1, r:  1, s:  0 },
+            HexDir::NW => Cube { q: -1, r:  0, s:  1 },
+        }
+    }
+
+    pub fn opposite(self) -> HexDir {
+        self.rotate_cw(3)
+    }
+
+    /// Rotate steps times 60º clockwise
+    pub fn rotate_cw(self, steps: u32) -> HexDir {
+        HexDir::ALL[(self.index() + steps as usize) % 6]
+    }
+
+    /// Rotate steps times 60º counterclockwise
+    pub fn rotate_ccw(self, steps: u32) -> HexDir {
+        HexDir::ALL[(self.index() + 6 - steps as usize % 6) % 6]
+    }
+
+    /// Position in HexDir::ALL
+    pub fn index(self) -> usize {
+        HexDir::ALL.iter().position(|&dir| dir == self).unwrap()
+    }
+}
+
+impl FromStr for HexDir {
+    type Err = ParseDirError;
+
+    fn from_str(s: &str) -> Result<HexDir, ParseDirError> {
+        match s {
+            "n"  => Ok(HexDir::N),
+            "ne" => Ok(HexDir::NE),
+            "se" => Ok(HexDir::SE),
+            "s"  => Ok(HexDir::S),
+            "sw" => Ok(HexDir::SW),
+            "nw" => Ok(HexDir::NW),
+            _ => Err(ParseDirError(String::from(s))),
+        }
+    }
+}
+
+impl fmt::Display for HexDir {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            HexDir::N => "n", HexDir::NE => "ne", HexDir::SE => "se",
+            HexDir::S => "s", HexDir::SW => "sw", HexDir::NW => "nw",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for ParseDirError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown direction \"{}\"", self.0)
+    }
+}
+
+impl Error for ParseDirError {}
+
+const SQRT_3: f64 = 1.732_050_807_568_877_2;
+
+impl Layout {
+    pub fn new(orientation: Orientation, size: f64) -> Layout {
+        Layout { orientation, size, origin: (0.0, 0.0) }
+    }
+
+    /// Center of the hexagon, with y growing down
+    pub fn to_pixel(&self, hex: Cube) -> (f64, f64) {
+        let (q, r) = (hex.q as f64, hex.r as f64);
+        let (x, y) = match self.orientation {
+            Orientation::FlatTop => (1.5 * q, SQRT_3 / 2.0 * q + SQRT_3 * r),
+            Orientation::PointyTop => (SQRT_3 * q + SQRT_3 / 2.0 * r, 1.5 * r),
+        };
+        (self.origin.0 + x * self.size, self.origin.1 + y * self.size)
+    }
+
+    /// Hexagon containing the pixel
+    pub fn from_pixel(&self, pixel: (f64, f64)) -> Cube {
+        let x = (pixel.0 - self.origin.0) / self.size;
+        let y = (pixel.1 - self.origin.1) / self.size;
+        let (q, r) = match self.orientation {
+            Orientation::FlatTop => (2.0 / 3.0 * x, -x / 3.0 + SQRT_3 / 3.0 * y),
+            Orientation::PointyTop => (SQRT_3 / 3.0 * x - y / 3.0, 2.0 / 3.0 * y),
+        };
+        Cube::round(q, r, -q - r)
+    }
+
+    /// Corners of the hexagon, clockwise
+    pub fn corners(&self, hex: Cube) -> [(f64, f64); 6] {
+        let (cx, cy) = self.to_pixel(hex);
+        let start = match self.orientation {
+            Orientation::FlatTop => 0.0,
+            Orientation::PointyTop => -30.0,
+        };
+        let mut corners = [(0.0, 0.0); 6];
+        for (i, corner) in corners.iter_mut().enumerate() {
+            let angle = (start + 60.0 * i as f64).to_radians();
+            *corner = (cx + self.size * angle.cos(), cy + self.size * angle.sin());
+        }
+        corners
+    }
+
+    /// Hexagon at the same pixel in the other layout
+    pub fn convert(&self, hex: Cube, other: &Layout) -> Cube {
+        other.from_pixel(self.to_pixel(hex))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn walk(moves: &str) -> Cube {
+        moves.split(',').map(|m| m.parse::<HexDir>().unwrap())
+            .fold(Cube::ORIGIN, Cube::neighbour)
+    }
+
+    #[test]
+    fn directions() {
+        assert_eq!(walk("ne,ne,ne").length(), 3);
+        assert_eq!(walk("ne,ne,sw,sw").length(), 0);
+        assert_eq!(walk("ne,ne,s,s").length(), 2);
+        assert_eq!(walk("se,sw,se,sw,sw").length(), 3);
+        assert_eq!("up".parse::<HexDir>(), Err(ParseDirError(String::from("up"))));
+
+        for &dir in HexDir::ALL.iter() {
+            assert_eq!(dir.to_string().parse::<HexDir>(), Ok(dir));
+            assert_eq!(dir.offset() + dir.opposite().offset(), Cube::ORIGIN);
+            assert_eq!(dir.offset().rotate_cw(), dir.rotate_cw(1).offset());
+            assert_eq!(dir.offset().rotate_ccw(), dir.rotate_ccw(1).offset());
+            assert_eq!(dir.rotate_cw(2).rotate_ccw(8), dir);
+        }
+        assert!(Cube::ORIGIN.neighbours().iter().all(|n| n.length() == 1));
+    }
+
+    #[test]
+    fn coordinates() {
+        let hex = Cube::new(3, -5, 2);
+        assert_eq!(Axial::from(hex), Axial::new(3, -5));
+        assert_eq!(Cube::from(Axial::new(3, -5)), hex);
+        assert_eq!(hex.distance(Cube::new(-1, 0, 1)), 5);
+        assert_eq!(hex.rotate_cw().rotate_cw().rotate_cw(), hex * -1);
+        assert_eq!(hex.rotate_ccw().rotate_cw(), hex);
+    }
+
+    #[test]
+    fn rings_and_spirals() {
+        assert_eq!(Cube::ORIGIN.ring(0), vec![Cube::ORIGIN]);
+        let center = Cube::new(2, -1, -1);
+        for radius in 1..5 {
+            let ring = center.ring(radius);
+            assert_eq!(ring.len(), 6 * radius as usize);
+            assert!(ring.iter().all(|&hex| hex.distance(center) == radius));
+            // consecutive hexagons, also from the last to the first
+            assert!(ring.iter().zip(ring.iter().cycle().skip(1)).all(|(a, &b)| a.distance(b) == 1));
+        }
+        let spiral = center.spiral(3);
+        assert_eq!(spiral.len(), 37);
+        assert_eq!(spiral.iter().collect::<std::collections::HashSet<_>>().len(), 37);
+    }
+
+    #[test]
+    fn lines() {
+        let (a, b) = (Cube::new(-2, 0, 2), Cube::new(3, -4, 1));
+        let line = a.line_to(b);
+        assert_eq!(line.len(), 6);
+        assert_eq!((line[0], line[5]), (a, b));
+        assert!(line.windows(2).all(|w| w[0].distance(w[1]) == 1));
+        assert_eq!(a.line_to(a), vec![a]);
+        assert_eq!(Cube::ORIGIN.line_to(walk("n,n,n")), vec![
+            Cube::ORIGIN, walk("n"), walk("n,n"), walk("n,n,n")]);
+    }
+
+    #[test]
+    fn layouts() {
+        let flat = Layout::new(Orientation::FlatTop, 10.0);
+        let pointy = Layout { origin: (100.0, 50.0), ..Layout::new(Orientation::PointyTop, 10.0) };
+        let (x, y) = flat.to_pixel(walk("n"));
+        assert!(x.abs() < 1e-9 && (y + 10.0 * SQRT_3).abs() < 1e-9);
+        let (x, y) = pointy.to_pixel(walk("se"));
+        assert!((x - 100.0 - 10.0 * SQRT_3).abs() < 1e-9 && (y - 50.0).abs() < 1e-9);
+
+        for hex in Cube::new(1, 1, -2).spiral(4) {
+            assert_eq!(flat.from_pixel(flat.to_pixel(hex)), hex);
+            assert_eq!(pointy.from_pixel(pointy.to_pixel(hex)), hex);
+            for corner in flat.corners(hex).iter() {
+                let (x, y) = flat.to_pixel(hex);
+                assert!(((corner.0 - x).hypot(corner.1 - y) - 10.0).abs() < 1e-9);
+            }
+        }
+        // the center of the pointy origin hexagon, at (100, 50), is inside this flat one
+        assert_eq!(pointy.convert(Cube::ORIGIN, &flat), Cube::new(7, -1, -6));
+    }
+}
@@ -0,0 +1,3 @@
+pub mod hex;
+pub mod route;
+pub mod render;
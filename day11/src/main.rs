@@ -1,68 +1,54 @@
 use std::fs;
 use std::error::Error;
-use std::cmp;
+use day11::hex::{Cube, HexDir};
+use day11::render;
+use day11::route::{self, compress, shortest_path, summary};
 
-/// Position in hexagonal grid can be indicated by 2 axes coordinates, but with
-/// one of the axes in angle. Then, of the 6 posibles direction of one hexagon,
-/// 4 are aligned with the axes and 2 are not. A movement in one of those 2 not
-/// aligned directions modify both X and Y coordinates (see function
-/// hexagonal_move)
-/// 
-///       Y       X
-///       |      /
-///       |     /
-///      ---   /
-///    /     \ 
-///    \     /
-///      ---
-/// 
+/// Positions are cube coordinates of a flat-top hexagonal grid, see the hex module
 fn main() -> Result<(),Box<dyn Error>> {
     let input = fs::read_to_string("input.txt")?;
-    let movs: Vec<&str> =  input.split(",").collect();
-    
-    let mut pos = (0, 0);
+    let movs = parse_moves(&input)?;
+
+    let mut pos = Cube::ORIGIN;
     let mut max_dist = 0;
 
-    for mov in movs {
-        pos = hexagonal_move(&pos, mov);
-        let dist = calc_distance(&pos);
+    for &mov in &movs {
+        pos = pos.neighbour(mov);
+        let dist = pos.length();
         if dist > max_dist { max_dist = dist; }
     }
 
-    let final_dist = calc_distance(&pos);
+    let final_dist = pos.length();
 
     println!("Part 1: final distance={}", final_dist);
     println!("Part 2: max. distance={}", max_dist);
 
-    Ok(())
-}
+    let path = shortest_path(pos);
+    println!("Shortest path ({} moves): {}", path.len(), summary(&path));
 
-fn hexagonal_move(pos: &(i32, i32), mov: &str) -> (i32, i32) {
-    match mov {
-        "n"  => (pos.0,     pos.1 + 1),
-        "nw" => (pos.0 - 1, pos.1 + 1),
-        "sw" => (pos.0 - 1, pos.1),
-        "s"  => (pos.0,     pos.1 - 1),
-        "se" => (pos.0 + 1, pos.1 - 1),
-        "ne" => (pos.0 + 1, pos.1),
-        _ => panic!(),
+    let compressed = compress(&movs);
+    if route::walk(&compressed.route) != pos {
+        return Err("compressed route doesn't end in the same hexagon".into());
     }
-}
-
-fn calc_distance(pos: &(i32, i32)) -> u32 {
-    let (mut x, mut y) = pos;
-    let mut steps = 0;
-
-    if x < 0 && y > 0 { // nw
-        steps += cmp::min(i32::abs(x), i32::abs(y));
-        x += steps;
-        y -= steps;
-    } else if x > 0 && y < 0 { // se
-        steps += cmp::min(i32::abs(x), i32::abs(y));
-        x -= steps;
-        y += steps;
+    println!("Compressed route: {} -> {} moves ({} pairs cancelled, {} pairs merged): {}",
+             movs.len(), compressed.route.len(), compressed.cancelled, compressed.merged,
+             summary(&compressed.route));
+
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--map" => fs::write("walk_map.txt", render::to_ascii(&movs))?,
+            "--svg" => fs::write("walk.svg", render::to_svg(&movs))?,
+            "--csv" => fs::write("distances.csv", render::distances_csv(&movs))?,
+            _ => return Err(format!("unknown option {}", arg).into()),
+        }
     }
-    steps += i32::abs(x) + i32::abs(y); // n, s, ne, sw
 
-    steps as u32
+    Ok(())
+}
+
+fn parse_moves(input: &str) -> Result<Vec<HexDir>, Box<dyn Error>> {
+    input.trim().split(',')
+        .enumerate()
+        .map(|(i, mov)| mov.parse().map_err(|e| format!("move {}: {}", i + 1, e).into()))
+        .collect()
 }
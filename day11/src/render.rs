@@ -0,0 +1,153 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+use super::hex::{Cube, HexDir, Layout, Orientation};
+
+const SVG_HEX_SIZE: f64 = 10.0;
+
+/// Positions after every move, starting at the origin
+pub fn positions(moves: &[HexDir]) -> Vec<Cube> {
+    let mut positions = Vec::with_capacity(moves.len() + 1);
+    positions.push(Cube::ORIGIN);
+    for &mov in moves {
+        positions.push(positions[positions.len() - 1].neighbour(mov));
+    }
+    positions
+}
+
+/// Index of the first position furthest from the origin
+pub fn furthest(positions: &[Cube]) -> usize {
+    let max_dist = positions.iter().map(|pos| pos.length()).max().unwrap_or(0);
+    positions.iter().position(|pos| pos.length() == max_dist).unwrap_or(0)
+}
+
+/// Map of the visited hexagons, with the columns of hexagons shifted half a
+/// row, as in a flat-top grid. Marks: O origin, E end, X furthest point.
+pub fn to_ascii(moves: &[HexDir]) -> String {
+    let positions = positions(moves);
+    // doubled rows: a hexagon to the north is 2 rows up, to the north-east 1 row up
+    let cell = |pos: &Cube| (pos.q, 2 * pos.r + pos.q);
+    let min_col = positions.iter().map(|pos| cell(pos).0).min().unwrap();
+    let max_col = positions.iter().map(|pos| cell(pos).0).max().unwrap();
+    let min_row = positions.iter().map(|pos| cell(pos).1).min().unwrap();
+    let max_row = positions.iter().map(|pos| cell(pos).1).max().unwrap();
+
+    let width = 2 * (max_col - min_col) as usize + 1;
+    let mut grid = vec![vec![b' '; width]; (max_row - min_row) as usize + 1];
+    let mut mark = |pos: &Cube, c: u8| {
+        let (col, row) = cell(pos);
+        grid[(row - min_row) as usize][2 * (col - min_col) as usize] = c;
+    };
+    for pos in &positions {
+        mark(pos, b'.');
+    }
+    mark(&Cube::ORIGIN, b'O');
+    mark(&positions[positions.len() - 1], b'E');
+    mark(&positions[furthest(&positions)], b'X');
+
+    let mut map = String::new();
+    for row in grid {
+        map += String::from_utf8(row).unwrap().trim_end();
+        map += "\n";
+    }
+    map
+}
+
+/// Visited hexagons and the path through them, with the origin in green, the
+/// end in blue and the furthest point in red
+pub fn to_svg(moves: &[HexDir]) -> String {
+    let positions = positions(moves);
+    let layout = Layout::new(Orientation::FlatTop, SVG_HEX_SIZE);
+    let pixels: Vec<(f64, f64)> = positions.iter().map(|&pos| layout.to_pixel(pos)).collect();
+
+    let min_x = pixels.iter().map(|p| p.0).fold(f64::INFINITY, f64::min) - SVG_HEX_SIZE;
+    let max_x = pixels.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max) + SVG_HEX_SIZE;
+    let min_y = pixels.iter().map(|p| p.1).fold(f64::INFINITY, f64::min) - SVG_HEX_SIZE;
+    let max_y = pixels.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max) + SVG_HEX_SIZE;
+
+    let mut svg = String::new();
+    writeln!(svg, "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{:.2} {:.2} {:.2} {:.2}\">",
+             min_x, min_y, max_x - min_x, max_y - min_y).unwrap();
+
+    let mut drawn = HashSet::new();
+    for &pos in &positions {
+        if drawn.insert(pos) {
+            let corners: Vec<String> = layout.corners(pos).iter()
+                .map(|(x, y)| format!("{:.2},{:.2}", x, y))
+                .collect();
+            writeln!(svg, "  <polygon points=\"{}\" fill=\"#eee\" stroke=\"#ccc\"/>", corners.join(" ")).unwrap();
+        }
+    }
+
+    let points: Vec<String> = pixels.iter().map(|(x, y)| format!("{:.2},{:.2}", x, y)).collect();
+    writeln!(svg, "  <polyline points=\"{}\" fill=\"none\" stroke=\"black\"/>", points.join(" ")).unwrap();
+
+    let end = positions.len() - 1;
+    let furthest = furthest(&positions);
+    for &(i, color, title) in &[(0, "green", "origin"), (end, "blue", "end"), (furthest, "red", "furthest")] {
+        let (x, y) = pixels[i];
+        writeln!(svg, "  <circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\" fill=\"{}\"><title>{} {}, step {}, distance {}</title></circle>",
+                 x, y, SVG_HEX_SIZE / 2.0, color, title, positions[i], i, positions[i].length()).unwrap();
+    }
+
+    svg += "</svg>\n";
+    svg
+}
+
+/// Distance to the origin after every move, and the maximum so far
+pub fn distances_csv(moves: &[HexDir]) -> String {
+    let mut csv = String::from("step,move,q,r,s,distance,max_distance\n");
+    let mut max_dist = 0;
+    for (step, (pos, mov)) in positions(moves).iter().skip(1).zip(moves).enumerate() {
+        max_dist = max_dist.max(pos.length());
+        writeln!(csv, "{},{},{},{},{},{},{}", step + 1, mov, pos.q, pos.r, pos.s, pos.length(), max_dist).unwrap();
+    }
+    csv
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn moves(moves: &str) -> Vec<HexDir> {
+        moves.split(',').map(|m| m.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn ascii_map() {
+        assert_eq!(to_ascii(&moves("ne,ne,s,s")), concat!(
+            "    X\n",
+            "  .\n",
+            "O   .\n",
+            "\n",
+            "    E\n"));
+        assert_eq!(to_ascii(&moves("n,n,n,s,se")), concat!(
+            "X\n",
+            "\n",
+            ".\n",
+            "  E\n",
+            ".\n",
+            "\n",
+            "O\n"));
+    }
+
+    #[test]
+    fn svg_export() {
+        let svg = to_svg(&moves("n,n,s,s"));
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"-10.00 -44.64 20.00 54.64\">\n"));
+        assert_eq!(svg.matches("<polygon").count(), 3);
+        assert!(svg.contains("<polyline points=\"0.00,0.00 0.00,-17.32 0.00,-34.64 0.00,-17.32 0.00,0.00\""));
+        assert!(svg.contains("fill=\"red\"><title>furthest (0, -2, 2), step 2, distance 2</title>"));
+        assert!(svg.ends_with("</svg>\n"));
+    }
+
+    #[test]
+    fn distance_timeline() {
+        assert_eq!(distances_csv(&moves("ne,ne,sw,s")), concat!(
+            "step,move,q,r,s,distance,max_distance\n",
+            "1,ne,1,-1,0,1,1\n",
+            "2,ne,2,-2,0,2,2\n",
+            "3,sw,1,-1,0,1,2\n",
+            "4,s,1,0,-1,1,2\n"));
+        assert_eq!(furthest(&positions(&moves("ne,ne,sw,ne,ne"))), 5);
+    }
+}
@@ -0,0 +1,127 @@
+use super::hex::{Cube, HexDir};
+
+/// Route with the same destination, and how many pairs of moves were cancelled
+/// (like ne,sw) and merged (like ne,s -> se) to get it
+#[derive(Debug, PartialEq)]
+pub struct Compression {
+    pub route: Vec<HexDir>,
+    pub cancelled: u32,
+    pub merged: u32,
+}
+
+pub fn walk(route: &[HexDir]) -> Cube {
+    route.iter().fold(Cube::ORIGIN, |pos, &mov| pos.neighbour(mov))
+}
+
+/// As the order of the moves doesn't change the destination, the moves are
+/// counted by direction and reduced until there aren't opposing moves, or
+/// moves 120º apart. Then at most 2 adjacent directions remain, and the route
+/// is as short as possible.
+pub fn compress(route: &[HexDir]) -> Compression {
+    let mut counts = [0; 6];
+    for &mov in route {
+        counts[mov.index()] += 1;
+    }
+    let (mut cancelled, mut merged) = (0, 0);
+
+    loop {
+        let mut changed = false;
+        for i in 0..6 {
+            let opposite = (i + 3) % 6;
+            let n = counts[i].min(counts[opposite]);
+            counts[i] -= n;
+            counts[opposite] -= n;
+            cancelled += n;
+
+            let apart = (i + 2) % 6;
+            let m = counts[i].min(counts[apart]);
+            counts[i] -= m;
+            counts[apart] -= m;
+            counts[(i + 1) % 6] += m;
+            merged += m;
+
+            changed |= n > 0 || m > 0;
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let route = HexDir::ALL.iter()
+        .flat_map(|&dir| std::iter::repeat_n(dir, counts[dir.index()] as usize))
+        .collect();
+    Compression { route, cancelled, merged }
+}
+
+/// One of the minimal move sequences from the origin
+pub fn shortest_path(to: Cube) -> Vec<HexDir> {
+    // any hexagon is q times se plus r times s, which is minimal after compression
+    let along = |n: i32, dir: HexDir| std::iter::repeat_n(
+        if n < 0 { dir.opposite() } else { dir }, n.unsigned_abs() as usize);
+    let route: Vec<HexDir> = along(to.q, HexDir::SE).chain(along(to.r, HexDir::S)).collect();
+    compress(&route).route
+}
+
+/// Route with the repeated moves counted, like "3 ne, 2 s"
+pub fn summary(route: &[HexDir]) -> String {
+    let mut runs: Vec<(usize, HexDir)> = Vec::new();
+    for &mov in route {
+        match runs.last_mut() {
+            Some((n, dir)) if *dir == mov => *n += 1,
+            _ => runs.push((1, mov)),
+        }
+    }
+    runs.iter().map(|(n, dir)| format!("{} {}", n, dir)).collect::<Vec<_>>().join(", ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn route(moves: &str) -> Vec<HexDir> {
+        moves.split(',').filter(|m| !m.is_empty()).map(|m| m.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn compression() {
+        assert_eq!(compress(&route("ne,s")), Compression { route: route("se"), cancelled: 0, merged: 1 });
+        assert_eq!(compress(&route("ne,sw")), Compression { route: route(""), cancelled: 1, merged: 0 });
+        assert_eq!(compress(&route("ne,ne,s,s")).route, route("se,se"));
+        assert_eq!(compress(&route("se,sw,se,sw,sw")).route, route("s,s,sw"));
+        // n,se -> ne, which cancels with sw
+        assert_eq!(compress(&route("n,se,sw,n")), Compression { route: route("n"), cancelled: 1, merged: 1 });
+
+        let routes = [
+            "n,n,se,sw,s,nw,ne,ne,s",
+            "ne,se,s,sw,nw,n,n,ne,se,s,sw,nw",
+            "sw,sw,se,n,nw,ne,se,se,s,n,sw,nw,nw",
+            "s,s,s,nw,ne,n,ne,se,sw,sw,n,se,nw,s,ne",
+            "se,nw,se,nw,ne,sw,n,s,n,n,se,ne,ne,sw,s,nw,se",
+        ];
+        for moves in routes.iter() {
+            let moves = route(moves);
+            let compressed = compress(&moves);
+            assert_eq!(walk(&compressed.route), walk(&moves), "route {:?}", moves);
+            assert_eq!(compressed.route.len() as u32, walk(&moves).length(), "route {:?}", moves);
+            assert_eq!(2 * compressed.cancelled + compressed.merged + compressed.route.len() as u32, moves.len() as u32);
+        }
+    }
+
+    #[test]
+    fn shortest_paths() {
+        assert!(shortest_path(Cube::ORIGIN).is_empty());
+        assert_eq!(shortest_path(walk(&route("ne,ne,s,s"))), route("se,se"));
+        for hex in Cube::ORIGIN.spiral(6) {
+            let path = shortest_path(hex);
+            assert_eq!(walk(&path), hex);
+            assert_eq!(path.len() as u32, hex.length());
+        }
+    }
+
+    #[test]
+    fn summaries() {
+        assert_eq!(summary(&route("ne,ne,ne,s,s")), "3 ne, 2 s");
+        assert_eq!(summary(&route("n,s,n")), "1 n, 1 s, 1 n");
+        assert_eq!(summary(&[]), "");
+    }
+}
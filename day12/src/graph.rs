@@ -0,0 +1,259 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::collections::hash_map::Entry;
+use std::fmt::Write;
+
+/// Pipe network as an adjacency graph, for queries about how the programs are
+/// connected and not only whether they are
+#[derive(Debug, Default)]
+pub struct PipeGraph {
+    adjacency: BTreeMap<u32, BTreeSet<u32>>,
+}
+
+/// Result of the depth-first search of articulation points and bridges
+struct Cuts {
+    points: Vec<u32>,
+    bridges: Vec<(u32, u32)>,
+}
+
+impl PipeGraph {
+    pub fn new() -> PipeGraph {
+        PipeGraph::default()
+    }
+
+    /// Pipes from a program to itself don't connect anything, only the program is added
+    pub fn add_pipe(&mut self, a: u32, b: u32) {
+        self.adjacency.entry(a).or_default();
+        self.adjacency.entry(b).or_default();
+        if a != b {
+            self.adjacency.get_mut(&a).unwrap().insert(b);
+            self.adjacency.get_mut(&b).unwrap().insert(a);
+        }
+    }
+
+    pub fn neighbours(&self, pid: u32) -> impl Iterator<Item = u32> + '_ {
+        self.adjacency.get(&pid).into_iter().flatten().copied()
+    }
+
+    /// PIDs from one program to the other, both included, through the fewest pipes
+    pub fn shortest_path(&self, from: u32, to: u32) -> Option<Vec<u32>> {
+        if !self.adjacency.contains_key(&from) || !self.adjacency.contains_key(&to) {
+            return None;
+        }
+        let mut prev = HashMap::new();
+        prev.insert(from, from);
+        let mut queue = VecDeque::from(vec![from]);
+
+        while let Some(pid) = queue.pop_front() {
+            if pid == to {
+                let mut path = vec![to];
+                while path[path.len() - 1] != from {
+                    path.push(prev[&path[path.len() - 1]]);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for other in self.neighbours(pid) {
+                if let Entry::Vacant(e) = prev.entry(other) {
+                    e.insert(pid);
+                    queue.push_back(other);
+                }
+            }
+        }
+        None
+    }
+
+    /// Number of pipes from the program to every program in its group
+    pub fn distances_from(&self, from: u32) -> HashMap<u32, u32> {
+        let mut distances = HashMap::new();
+        distances.insert(from, 0);
+        let mut queue = VecDeque::from(vec![from]);
+
+        while let Some(pid) = queue.pop_front() {
+            let dist = distances[&pid];
+            for other in self.neighbours(pid) {
+                distances.entry(other).or_insert_with(|| {
+                    queue.push_back(other);
+                    dist + 1
+                });
+            }
+        }
+        distances
+    }
+
+    /// Groups of connected programs, each one sorted, by their lowest PID
+    pub fn components(&self) -> Vec<Vec<u32>> {
+        let mut seen: HashSet<u32> = HashSet::new();
+        let mut components = Vec::new();
+        for &pid in self.adjacency.keys() {
+            if seen.contains(&pid) {
+                continue;
+            }
+            let mut component: Vec<u32> = self.distances_from(pid).into_keys().collect();
+            component.sort();
+            seen.extend(&component);
+            components.push(component);
+        }
+        components
+    }
+
+    /// Longest of the shortest paths between 2 programs of the component, in pipes
+    pub fn diameter(&self, component: &[u32]) -> u32 {
+        component.iter()
+            .map(|&pid| self.distances_from(pid).values().copied().max().unwrap_or(0))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Programs whose removal would split their group
+    pub fn articulation_points(&self) -> Vec<u32> {
+        self.cuts().points
+    }
+
+    /// Pipes whose removal would split their group, with the lowest PID first
+    pub fn bridges(&self) -> Vec<(u32, u32)> {
+        self.cuts().bridges
+    }
+
+    /// Tarjan's algorithm: in the depth-first search tree, low is the earliest
+    /// discovered program reachable from the subtree with one pipe not in the
+    /// tree. If the subtree of a child can't reach above its parent, the
+    /// parent is an articulation point, and if it can't reach the parent
+    /// either, the pipe between them is a bridge.
+    fn cuts(&self) -> Cuts {
+        let pids: Vec<u32> = self.adjacency.keys().copied().collect();
+        let ids: HashMap<u32, usize> = pids.iter().enumerate().map(|(id, &pid)| (pid, id)).collect();
+        let adjacency: Vec<Vec<usize>> = self.adjacency.values()
+            .map(|others| others.iter().map(|pid| ids[pid]).collect())
+            .collect();
+
+        let mut disc = vec![usize::MAX; pids.len()];
+        let mut low = vec![0; pids.len()];
+        let mut is_point = vec![false; pids.len()];
+        let mut bridges = Vec::new();
+        let mut time = 0;
+
+        for root in 0..pids.len() {
+            if disc[root] != usize::MAX {
+                continue;
+            }
+            disc[root] = time;
+            low[root] = time;
+            time += 1;
+            let mut root_children = 0;
+            // iterative, with (program, parent, next neighbour to visit)
+            let mut stack = vec![(root, usize::MAX, 0)];
+
+            while let Some((id, parent, next)) = stack.last_mut() {
+                let (id, parent) = (*id, *parent);
+                if let Some(&other) = adjacency[id].get(*next) {
+                    *next += 1;
+                    if disc[other] == usize::MAX {
+                        disc[other] = time;
+                        low[other] = time;
+                        time += 1;
+                        if id == root {
+                            root_children += 1;
+                        }
+                        stack.push((other, id, 0));
+                    } else if other != parent {
+                        low[id] = low[id].min(disc[other]);
+                    }
+                    continue;
+                }
+
+                stack.pop();
+                if parent != usize::MAX {
+                    low[parent] = low[parent].min(low[id]);
+                    if low[id] > disc[parent] {
+                        let (a, b) = (pids[parent], pids[id]);
+                        bridges.push((a.min(b), a.max(b)));
+                    }
+                    if parent != root && low[id] >= disc[parent] {
+                        is_point[parent] = true;
+                    }
+                }
+            }
+            is_point[root] = root_children > 1;
+        }
+
+        bridges.sort();
+        let points = pids.iter().zip(is_point).filter(|(_, p)| *p).map(|(&pid, _)| pid).collect();
+        Cuts { points, bridges }
+    }
+
+    pub fn to_json(&self) -> String {
+        let cuts = self.cuts();
+        let mut json = String::from("{\"components\":[");
+
+        for (i, component) in self.components().iter().enumerate() {
+            if i > 0 {
+                json += ",";
+            }
+            write!(json, "{{\"size\":{},\"diameter\":{},\"pids\":[{}]}}", component.len(),
+                   self.diameter(component), join(component.iter().map(u32::to_string))).unwrap();
+        }
+        write!(json, "],\"articulation_points\":[{}],\"bridges\":[{}]}}",
+               join(cuts.points.iter().map(u32::to_string)),
+               join(cuts.bridges.iter().map(|(a, b)| format!("[{},{}]", a, b)))).unwrap();
+        json
+    }
+}
+
+fn join(items: impl Iterator<Item = String>) -> String {
+    items.collect::<Vec<_>>().join(",")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn build(pipes: &[(u32, u32)]) -> PipeGraph {
+        let mut graph = PipeGraph::new();
+        for &(a, b) in pipes {
+            graph.add_pipe(a, b);
+        }
+        graph
+    }
+
+    fn test_graph() -> PipeGraph {
+        build(&[(0, 2), (1, 1), (2, 3), (2, 4), (3, 4), (4, 6), (5, 6)])
+    }
+
+    #[test]
+    fn paths() {
+        let graph = test_graph();
+        assert_eq!(graph.shortest_path(0, 5), Some(vec![0, 2, 4, 6, 5]));
+        assert_eq!(graph.shortest_path(3, 3), Some(vec![3]));
+        assert_eq!(graph.shortest_path(0, 1), None);
+        assert_eq!(graph.shortest_path(0, 9), None);
+        assert_eq!(graph.components(), vec![vec![0, 2, 3, 4, 5, 6], vec![1]]);
+        assert_eq!(graph.diameter(&[0, 2, 3, 4, 5, 6]), 4);
+        assert_eq!(graph.diameter(&[1]), 0);
+    }
+
+    #[test]
+    fn cuts() {
+        let graph = test_graph();
+        assert_eq!(graph.articulation_points(), vec![2, 4, 6]);
+        assert_eq!(graph.bridges(), vec![(0, 2), (4, 6), (5, 6)]);
+
+        // a cycle has neither, 2 cycles joined by a program only an articulation point
+        let graph = build(&[(0, 1), (1, 2), (2, 0), (2, 3), (3, 4), (4, 2)]);
+        assert_eq!(graph.articulation_points(), vec![2]);
+        assert!(graph.bridges().is_empty());
+
+        // chain deep enough to overflow the 2 MiB stack of a test thread if the
+        // depth-first search recursed
+        let graph = build(&(0..50_000).map(|pid| (pid, pid + 1)).collect::<Vec<_>>());
+        assert_eq!(graph.articulation_points().len(), 49_999);
+        assert_eq!(graph.bridges().len(), 50_000);
+    }
+
+    #[test]
+    fn json_export() {
+        assert_eq!(test_graph().to_json(), concat!(
+            "{\"components\":[{\"size\":6,\"diameter\":4,\"pids\":[0,2,3,4,5,6]},",
+            "{\"size\":1,\"diameter\":0,\"pids\":[1]}],",
+            "\"articulation_points\":[2,4,6],\"bridges\":[[0,2],[4,6],[5,6]]}"));
+    }
+}
@@ -0,0 +1,3 @@
+pub mod network;
+pub mod graph;
+pub mod validate;
@@ -1,38 +1,67 @@
 use std::error::Error;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{BufRead, BufReader};
-use std::collections::BTreeSet;
-
-type Group = BTreeSet<u32>;
+use day12::graph::PipeGraph;
+use day12::network::PipeNetwork;
+use day12::validate;
 
 fn main() -> Result<(), Box<dyn Error>> {
+    // --path A B prints the shortest path between 2 programs, and --json saves
+    // the groups, articulation points and bridges in network.json.
+    // --validate only checks the input and lists the problems found
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if args.iter().any(|a| a == "--validate") {
+        let findings = validate::validate(&fs::read_to_string("input.txt")?);
+        for finding in &findings {
+            println!("{}", finding);
+        }
+        return match findings.len() {
+            0 => {
+                println!("input.txt is valid");
+                Ok(())
+            },
+            n => Err(format!("{} problems found in input.txt", n).into()),
+        };
+    }
+
     let file = File::open("input.txt")?;
     let reader = BufReader::new(file);
     let lines = reader.lines().map(|l| l.unwrap());
 
-    let mut groups: Vec<Group> = Vec::new();
+    let mut network = PipeNetwork::new();
+    let mut graph = PipeGraph::new();
 
     for line in lines {
         let procs = parse_line(&line);
-        let procs_groups_idxs = find_existing_groups(&procs, &groups);
-        let mut new_group: Group = procs.into_iter().collect();
-
-        if let Some(idxs) = procs_groups_idxs {
-            for &idx in idxs.iter().rev() {
-                new_group.append(&mut groups[idx]);
-                groups.remove(idx);
-            }
+        let proc = procs[procs.len() - 1];
+        for &other in &procs {
+            network.add_pipe(proc, other);
+            graph.add_pipe(proc, other);
         }
-
-        groups.push(new_group);
     }
 
-    let group0_len = groups.iter()
-                           .find_map(|g| g.contains(&0).then(|| g.len()))
-                           .unwrap_or(0);
+    let group0_len = network.component_size(0).unwrap_or(0);
 
     println!("Part 1: PID 0's group len={}", group0_len);
-    println!("Part 2: num of groups={}", groups.len());
+    println!("Part 2: num of groups={}", network.component_count());
+
+    if let Some(pos) = args.iter().position(|a| a == "--path") {
+        let pid = |i: usize| -> Result<u32, Box<dyn Error>> {
+            Ok(args.get(pos + i).ok_or("missing PID for --path")?.parse()?)
+        };
+        let (from, to) = (pid(1)?, pid(2)?);
+        match graph.shortest_path(from, to) {
+            Some(path) => {
+                let path: Vec<String> = path.iter().map(u32::to_string).collect();
+                println!("Path {} -> {} ({} pipes): {}", from, to, path.len() - 1, path.join(" -> "));
+            },
+            None => println!("{} and {} aren't connected", from, to),
+        }
+    }
+    if args.iter().any(|a| a == "--json") {
+        fs::write("network.json", graph.to_json())?;
+    }
     Ok(())
 }
 
@@ -50,22 +79,3 @@ fn parse_line(line: &str) -> Vec<u32> {
     
     procs
 }
-
-fn find_existing_groups(procs: &Vec<u32>, groups: &Vec<Group>) -> Option<Vec<usize>> {
-    let mut idxs = Vec::new();
-
-    for proc in procs {
-        for (i, group) in groups.iter().enumerate() {
-            if group.contains(proc) {
-                idxs.push(i);
-            }
-        }
-    }
-    idxs.sort();
-    idxs.dedup();
-
-    match idxs.len() {
-        0 => None,
-        _ => Some(idxs)
-    }
-}
@@ -0,0 +1,161 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+
+/// Groups of programs that can communicate through pipes, kept as a disjoint
+/// set forest: every program points to a parent in its group, and the root is
+/// the representative of the group. Finding the root compresses the path,
+/// so queries take &self and the parents are in cells.
+#[derive(Debug, Default)]
+pub struct PipeNetwork {
+    ids: HashMap<u32, usize>,
+    pids: Vec<u32>,
+    parents: Vec<Cell<usize>>,
+    ranks: Vec<u8>,
+    sizes: Vec<usize>, // only valid for roots
+    count: usize,
+}
+
+impl PipeNetwork {
+    pub fn new() -> PipeNetwork {
+        PipeNetwork::default()
+    }
+
+    /// Add a program in its own group, if it isn't already in the network
+    pub fn add_program(&mut self, pid: u32) {
+        self.insert(pid);
+    }
+
+    fn insert(&mut self, pid: u32) -> usize {
+        if let Some(&id) = self.ids.get(&pid) {
+            return id;
+        }
+        let id = self.pids.len();
+        self.ids.insert(pid, id);
+        self.pids.push(pid);
+        self.parents.push(Cell::new(id));
+        self.ranks.push(0);
+        self.sizes.push(1);
+        self.count += 1;
+        id
+    }
+
+    /// Join the groups of both programs, adding them if needed
+    pub fn add_pipe(&mut self, a: u32, b: u32) {
+        let (id_a, id_b) = (self.insert(a), self.insert(b));
+        let (root_a, root_b) = (self.find(id_a), self.find(id_b));
+        if root_a == root_b {
+            return;
+        }
+
+        // union by rank: the shorter tree goes below the root of the taller one
+        let (root, child) = if self.ranks[root_a] < self.ranks[root_b] { (root_b, root_a) }
+                            else { (root_a, root_b) };
+        self.parents[child].set(root);
+        self.sizes[root] += self.sizes[child];
+        if self.ranks[root] == self.ranks[child] {
+            self.ranks[root] += 1;
+        }
+        self.count -= 1;
+    }
+
+    pub fn contains(&self, pid: u32) -> bool {
+        self.ids.contains_key(&pid)
+    }
+
+    pub fn connected(&self, a: u32, b: u32) -> bool {
+        match (self.component_of(a), self.component_of(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    /// PID representing the group of the program
+    pub fn component_of(&self, pid: u32) -> Option<u32> {
+        self.ids.get(&pid).map(|&id| self.pids[self.find(id)])
+    }
+
+    pub fn component_count(&self) -> usize {
+        self.count
+    }
+
+    pub fn component_size(&self, pid: u32) -> Option<usize> {
+        self.ids.get(&pid).map(|&id| self.sizes[self.find(id)])
+    }
+
+    /// Size of every group, by the PID representing it
+    pub fn component_sizes(&self) -> HashMap<u32, usize> {
+        (0..self.pids.len())
+            .filter(|&id| self.find(id) == id)
+            .map(|id| (self.pids[id], self.sizes[id]))
+            .collect()
+    }
+
+    fn find(&self, id: usize) -> usize {
+        let mut root = id;
+        while self.parents[root].get() != root {
+            root = self.parents[root].get();
+        }
+
+        // path compression: everything on the way now points to the root
+        let mut id = id;
+        while id != root {
+            id = self.parents[id].replace(root);
+        }
+        root
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn test_network() -> PipeNetwork {
+        let mut network = PipeNetwork::new();
+        for &(pid, pipes) in &[(0, &[2][..]), (1, &[1]), (2, &[0, 3, 4]), (3, &[2, 4]),
+                               (4, &[2, 3, 6]), (5, &[6]), (6, &[4, 5])] {
+            for &other in pipes {
+                network.add_pipe(pid, other);
+            }
+        }
+        network
+    }
+
+    #[test]
+    fn components() {
+        let network = test_network();
+        assert_eq!(network.component_count(), 2);
+        assert_eq!(network.component_size(0), Some(6));
+        assert_eq!(network.component_size(1), Some(1));
+        assert_eq!(network.component_size(7), None);
+        assert!(network.connected(0, 5));
+        assert!(!network.connected(0, 1));
+        assert!(!network.connected(0, 7));
+        assert_eq!(network.component_of(5), network.component_of(3));
+        assert_eq!(network.component_of(1), Some(1));
+
+        let mut sizes: Vec<usize> = network.component_sizes().values().copied().collect();
+        sizes.sort();
+        assert_eq!(sizes, vec![1, 6]);
+    }
+
+    #[test]
+    fn long_chain() {
+        // many unions in both orders, union by rank must keep the ranks within
+        // log2 of the component sizes
+        let n = 1 << 16;
+        let mut network = PipeNetwork::new();
+        for pid in 0..n {
+            network.add_pipe(pid, pid + 1);
+            if pid < n - 1 {
+                network.add_pipe(2 * n - pid, 2 * n - 1 - pid);
+            }
+        }
+        network.add_program(3 * n);
+        assert_eq!(network.component_count(), 3);
+        assert!(network.connected(0, n));
+        assert!(!network.connected(n, n + 1));
+        assert_eq!(network.component_size(0), Some(n as usize + 1));
+        assert_eq!(network.component_size(2 * n), Some(n as usize));
+        assert!(network.ranks.iter().all(|&rank| rank <= 16));
+    }
+}
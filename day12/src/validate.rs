@@ -0,0 +1,151 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum Issue {
+    Malformed(String),
+    DuplicatedLine { pid: u32, first_line: usize },
+    DuplicatedPipe { pid: u32, other: u32 },
+    SelfPipe(u32),
+    OneSided { pid: u32, other: u32, other_line: usize },
+    MissingLine(u32),
+    MissingPids { from: u32, to: u32 },
+}
+
+/// Problem found in the input, at the line where it's easiest to fix
+#[derive(Debug, PartialEq)]
+pub struct Finding {
+    pub line: usize,
+    pub issue: Issue,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.issue {
+            Issue::Malformed(line) => write!(f, "can't parse \"{}\"", line),
+            Issue::DuplicatedLine { pid, first_line } =>
+                write!(f, "PID {} already has a line, line {}", pid, first_line),
+            Issue::DuplicatedPipe { pid, other } => write!(f, "pipe {} <-> {} listed twice", pid, other),
+            Issue::SelfPipe(pid) => write!(f, "pipe from PID {} to itself", pid),
+            Issue::OneSided { pid, other, other_line } =>
+                write!(f, "pipe {} <-> {} missing from the line of PID {}, line {}", pid, other, other, other_line),
+            Issue::MissingLine(pid) => write!(f, "PID {} doesn't have its own line", pid),
+            Issue::MissingPids { from, to } if from == to => write!(f, "PID {} missing before this line", from),
+            Issue::MissingPids { from, to } => write!(f, "PIDs {}-{} missing before this line", from, to),
+        }
+    }
+}
+
+/// Check that every pipe is listed from both sides, once, between different
+/// programs, and that all the PIDs from 0 to the highest one have a line.
+/// A line like "1 <-> 1" is how a program without pipes is listed, so a pipe
+/// to itself is only a problem along with other pipes.
+pub fn validate(input: &str) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut lines: HashMap<u32, (usize, Vec<u32>)> = HashMap::new();
+    let mut entries = Vec::new();
+
+    for (i, line) in input.lines().enumerate() {
+        let line_num = i + 1;
+        let (pid, pipes) = match parse_entry(line) {
+            Some(entry) => entry,
+            None => {
+                findings.push(Finding { line: line_num, issue: Issue::Malformed(String::from(line)) });
+                continue;
+            },
+        };
+
+        for (j, &other) in pipes.iter().enumerate() {
+            if other == pid && pipes.len() > 1 {
+                findings.push(Finding { line: line_num, issue: Issue::SelfPipe(pid) });
+            } else if pipes[..j].contains(&other) {
+                findings.push(Finding { line: line_num, issue: Issue::DuplicatedPipe { pid, other } });
+            }
+        }
+
+        match lines.get(&pid) {
+            Some(&(first_line, _)) =>
+                findings.push(Finding { line: line_num, issue: Issue::DuplicatedLine { pid, first_line } }),
+            None => {
+                lines.insert(pid, (line_num, pipes.clone()));
+            },
+        }
+        entries.push((line_num, pid, pipes));
+    }
+
+    // first line where each PID without its own line is listed
+    let mut missing: BTreeMap<u32, usize> = BTreeMap::new();
+    for (line_num, pid, pipes) in &entries {
+        for &other in pipes {
+            match lines.get(&other) {
+                Some((other_line, other_pipes)) if !other_pipes.contains(pid) => findings.push(Finding {
+                    line: *line_num, issue: Issue::OneSided { pid: *pid, other, other_line: *other_line }
+                }),
+                Some(_) => (),
+                None => {
+                    missing.entry(other).or_insert(*line_num);
+                },
+            }
+        }
+    }
+    findings.extend(missing.into_iter().map(|(pid, line)| Finding { line, issue: Issue::MissingLine(pid) }));
+
+    let mut pids: Vec<(u32, usize)> = lines.iter().map(|(&pid, &(line, _))| (pid, line)).collect();
+    pids.sort();
+    let mut expected = 0;
+    for (pid, line) in pids {
+        if pid > expected {
+            findings.push(Finding { line, issue: Issue::MissingPids { from: expected, to: pid - 1 } });
+        }
+        expected = pid + 1;
+    }
+
+    findings.sort_by_key(|finding| finding.line);
+    findings
+}
+
+fn parse_entry(line: &str) -> Option<(u32, Vec<u32>)> {
+    let mut split = line.split(" <-> ");
+    let pid = split.next()?.trim().parse().ok()?;
+    let pipes = split.next()?.split(',')
+        .map(|s| s.trim().parse().ok())
+        .collect::<Option<Vec<u32>>>()?;
+    match split.next() {
+        None => Some((pid, pipes)),
+        Some(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn valid_input() {
+        assert_eq!(validate("0 <-> 2\n1 <-> 1\n2 <-> 0, 3\n3 <-> 2\n"), vec![]);
+        // the puzzle input only has redundant pipes to the same program
+        assert!(validate(&std::fs::read_to_string("input.txt").unwrap()).iter()
+                .all(|f| matches!(f.issue, Issue::SelfPipe(_))));
+    }
+
+    #[test]
+    fn findings() {
+        let findings = validate("0 <-> 2, 2\n1 <-> 4\n2 <-> 0\n2 <-> 1\n5 <-> 1, 5\n6 <-> 2, x\n7 <-> 8\n");
+        let expects = vec![
+            Finding { line: 1, issue: Issue::DuplicatedPipe { pid: 0, other: 2 } },
+            Finding { line: 2, issue: Issue::MissingLine(4) },
+            Finding { line: 4, issue: Issue::DuplicatedLine { pid: 2, first_line: 3 } },
+            Finding { line: 4, issue: Issue::OneSided { pid: 2, other: 1, other_line: 2 } },
+            Finding { line: 5, issue: Issue::SelfPipe(5) },
+            Finding { line: 5, issue: Issue::OneSided { pid: 5, other: 1, other_line: 2 } },
+            Finding { line: 5, issue: Issue::MissingPids { from: 3, to: 4 } },
+            Finding { line: 6, issue: Issue::Malformed(String::from("6 <-> 2, x")) },
+            Finding { line: 7, issue: Issue::MissingLine(8) },
+            Finding { line: 7, issue: Issue::MissingPids { from: 6, to: 6 } },
+        ];
+        assert_eq!(findings, expects);
+        assert_eq!(findings[3].to_string(), "line 4: pipe 2 <-> 1 missing from the line of PID 1, line 2");
+        assert_eq!(findings[6].to_string(), "line 5: PIDs 3-4 missing before this line");
+    }
+}
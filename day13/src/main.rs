@@ -1,70 +1,180 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+
+mod sim;
+
+/// Max. number of allowed delays kept while sieving, before switching to
+/// checking the delays one by one
+const SIEVE_LIMIT: usize = 1 << 20;
+/// Max. number of delays checked one by one before giving up
+const SEARCH_LIMIT: u64 = 1 << 32;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    // --frames DELAY draws the crossing of the packet after waiting DELAY, and
+    // --catches N lists the layers that catch the packet for delays 0 to N-1
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let arg_value = |arg: &str| -> Result<Option<u64>, Box<dyn Error>> {
+        match args.iter().position(|a| a == arg) {
+            Some(pos) => Ok(Some(args.get(pos + 1).ok_or(format!("missing value for {}", arg))?.parse()?)),
+            None => Ok(None),
+        }
+    };
 
-fn main() {
     let layers_lens = parse_layers_lens("input.txt");
 
     let penalty = calc_penalty_crossing(&layers_lens);
     println!("Part 1: penalty points={}", penalty);
     
-    let delay = calc_min_required_delay(&layers_lens);
-    println!("Part 2: delay={}", delay);
+    match find_min_delay(&layers_lens)? {
+        Some(delay) => println!("Part 2: delay={}", delay),
+        None => println!("Part 2: the packet is caught with any delay"),
+    }
+
+    if let Some(delay) = arg_value("--frames")? {
+        let mut frames = String::new();
+        let caught = sim::simulate(&layers_lens, delay, Some(&mut frames));
+        print!("{}", frames);
+        println!("Caught in layers {:?}", caught);
+    }
+    if let Some(delays) = arg_value("--catches")? {
+        for delay in 0..delays {
+            let layers: Vec<String> = catching_layers(&layers_lens, delay).iter().map(usize::to_string).collect();
+            println!("delay {}: {}", delay, if layers.is_empty() { String::from("-") } else { layers.join(", ") });
+        }
+    }
+
+    Ok(())
 }
 
-fn calc_penalty_crossing(layers_lens: &[usize]) -> u32 {
+fn calc_penalty_crossing(layers_lens: &[usize]) -> u64 {
     let mut penalty_points = 0;
 
-    for pos in 0..layers_lens.len() {
-        let layer_len = layers_lens[pos];
-        let arrival_time = pos as u32;
+    for (pos, &layer_len) in layers_lens.iter().enumerate() {
+        let arrival_time = pos as u64;
         let interval = calc_interval(layer_len);
         if packet_caught(arrival_time, interval) {
-            penalty_points += (pos * layer_len) as u32;
+            penalty_points += (pos * layer_len) as u64;
         }
     }
 
     penalty_points
 }
 
-fn calc_min_required_delay(layers_lens: &[usize]) -> u32 {
-    let mut delay = 0u32;
+/// Layers that catch the packet if it waits delay picoseconds
+fn catching_layers(layers_lens: &[usize], delay: u64) -> Vec<usize> {
+    (0..layers_lens.len())
+        .filter(|&pos| packet_caught(delay + pos as u64, calc_interval(layers_lens[pos])))
+        .collect()
+}
 
-    loop {
-        let mut caught = false;
+/// Minimum delay to cross without being caught, or None if there isn't any.
+/// The packet is caught in a layer if delay + pos is a multiple of its
+/// interval, so each layer forbids the delays with residue -pos modulo its
+/// interval. The allowed residues are sieved layer by layer, modulo the LCM of
+/// the intervals seen so far. If they become too many, the remaining layers
+/// are checked for each allowed delay in order, failing if none is found after
+/// checking search_limit delays.
+fn find_min_delay(layers_lens: &[usize]) -> Result<Option<u64>, String> {
+    find_min_delay_with_limits(layers_lens, SIEVE_LIMIT, SEARCH_LIMIT)
+}
+
+fn find_min_delay_with_limits(layers_lens: &[usize], sieve_limit: usize, search_limit: u64) -> Result<Option<u64>, String> {
+    // forbidden residues by interval
+    let mut forbidden: BTreeMap<u64, Vec<u64>> = BTreeMap::new();
+    for (pos, &len) in layers_lens.iter().enumerate() {
+        match calc_interval(len) {
+            None => (),
+            Some(0) => return Ok(None), // a scanner with range 1 is always at the top
+            Some(interval) => forbidden.entry(interval).or_default().push((interval - pos as u64 % interval) % interval),
+        }
+    }
+
+    let mut modulus = 1;
+    let mut allowed = vec![0];
+    let mut intervals = forbidden.iter();
+    let mut remaining = Vec::new();
 
-        for (pos, &len) in layers_lens.iter().enumerate() {
-            let interval = calc_interval(len);
-            let arrival_time = delay + pos as u32;
-            if packet_caught(arrival_time, interval) {
-                caught = true;
+    for (&interval, residues) in &mut intervals {
+        let new_modulus = lcm(modulus, interval);
+        let expanded = new_modulus.and_then(|m| allowed.len().checked_mul((m / modulus) as usize));
+        match (new_modulus, expanded) {
+            (Some(new_modulus), Some(expanded)) if expanded <= sieve_limit => {
+                // allowed stays sorted: the residues r + k*modulus are generated in order
+                allowed = (0..new_modulus / modulus)
+                    .flat_map(|k| allowed.iter().map(move |&r| r + k * modulus))
+                    .filter(|&delay| !residues.contains(&(delay % interval)))
+                    .collect();
+                modulus = new_modulus;
+                if allowed.is_empty() {
+                    return Ok(None);
+                }
+            },
+            _ => {
+                remaining.push((interval, residues));
                 break;
-            }
+            },
         }
+    }
+    remaining.extend(intervals.map(|(interval, residues)| (*interval, residues)));
+
+    if remaining.is_empty() {
+        return Ok(Some(allowed[0]));
+    }
 
-        match caught {
-            true  => delay += 1,
-            false => break delay,
+    // the delays repeat with the LCM of all the intervals, if it fits in u64
+    let period = remaining.iter().try_fold(modulus, |m, &(interval, _)| lcm(m, interval));
+    let mut base = 0;
+    let mut checked = 0;
+    loop {
+        for &r in &allowed {
+            let delay = base + r;
+            if remaining.iter().all(|(interval, residues)| !residues.contains(&(delay % interval))) {
+                return Ok(Some(delay));
+            }
+        }
+        base += modulus;
+        if period.is_some_and(|period| base >= period) {
+            return Ok(None);
+        }
+        checked += allowed.len() as u64;
+        if checked >= search_limit {
+            return Err(format!("no delay found after checking {} delays, up to {}", checked, base - 1));
         }
     }
 }
 
+fn lcm(a: u64, b: u64) -> Option<u64> {
+    (a / gcd(a, b)).checked_mul(b)
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        let tmp = a % b;
+        a = b;
+        b = tmp;
+    }
+    a
+}
+
 #[inline]
-fn calc_interval(len: usize) -> Option<u32> {
+fn calc_interval(len: usize) -> Option<u64> {
     match len {
         0 => None,
-        l => Some(2 * (l as u32 - 1)), 
+        l => Some(2 * (l as u64 - 1)), 
     }
 }
 
 #[inline]
-fn packet_caught(arrival_time: u32, interval: Option<u32>) -> bool {
+fn packet_caught(arrival_time: u64, interval: Option<u64>) -> bool {
     match interval {
         None => false,
-        Some(0) => panic!(),
+        Some(0) => true, // range 1, the scanner never leaves the top
         Some(interval) => is_even_divisible(arrival_time, interval),
     }
 }
 
 #[inline]
-fn is_even_divisible(dividend: u32, divisor: u32) -> bool {
+fn is_even_divisible(dividend: u64, divisor: u64) -> bool {
     dividend == (dividend / divisor) * divisor
 }
 
@@ -88,3 +198,77 @@ fn parse_line(line: &str) -> (usize, usize) {
     let len = split.next().unwrap().parse().unwrap();
     (num, len)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn brute_force_delay(layers_lens: &[usize], max_delay: u64) -> Option<u64> {
+        (0..max_delay).find(|&delay| layers_lens.iter().enumerate()
+            .all(|(pos, &len)| !packet_caught(delay + pos as u64, calc_interval(len))))
+    }
+
+    #[test]
+    fn example() {
+        let layers_lens = [3, 2, 0, 0, 4, 0, 4];
+        assert_eq!(calc_penalty_crossing(&layers_lens), 24);
+        assert_eq!(find_min_delay(&layers_lens), Ok(Some(10)));
+        assert_eq!(find_min_delay_with_limits(&layers_lens, 1, SEARCH_LIMIT), Ok(Some(10)));
+        assert_eq!(find_min_delay(&[]), Ok(Some(0)));
+    }
+
+    #[test]
+    fn no_delay() {
+        assert_eq!(find_min_delay(&[3, 0, 1]), Ok(None));
+        // interval 2 with both residues forbidden
+        assert_eq!(find_min_delay(&[2, 2]), Ok(None));
+        // only forbidden when combined: even delays by the 1st layer, odd ones by the 2nd and 3rd
+        assert_eq!(find_min_delay(&[2, 3, 0, 3]), Ok(None));
+        assert_eq!(find_min_delay_with_limits(&[2, 3, 0, 3], 1, SEARCH_LIMIT), Ok(None));
+    }
+
+    #[test]
+    fn search_limit() {
+        // the 1st two layers catch the packet with any delay, but the intervals
+        // of all the layers are too many to sieve and their LCM overflows u64
+        let primes = [3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53];
+        let layers_lens: Vec<usize> = [2, 2].iter().copied().chain(primes.iter().map(|p| p + 1)).collect();
+        assert_eq!(find_min_delay(&layers_lens), Ok(None));
+        assert!(find_min_delay_with_limits(&layers_lens, 1, 1000).is_err());
+        // the 1st delay that works is the 11th one checked
+        assert_eq!(find_min_delay_with_limits(&[3, 2, 0, 0, 4, 0, 4], 1, 11), Ok(Some(10)));
+        assert!(find_min_delay_with_limits(&[3, 2, 0, 0, 4, 0, 4], 1, 10).is_err());
+    }
+
+    #[test]
+    fn same_as_brute_force() {
+        let tests: [(&[usize], Option<u64>); 13] = [
+            (&[2, 4, 5, 0, 5, 2], None),
+            (&[5, 4, 2, 2, 0, 0, 2], None),
+            (&[2, 2, 3, 3], None),
+            (&[5, 5, 3, 2, 3], None),
+            (&[4, 2, 0, 0, 5, 5, 5, 3], Some(8)),
+            (&[0, 3, 5, 0, 2, 4, 0, 4], Some(9)),
+            (&[5, 3, 5, 2, 4, 5, 6], Some(10)),
+            (&[2, 5, 3, 4, 2, 6, 0, 5], Some(11)),
+            (&[5, 3, 4, 3, 6, 3, 5, 5], Some(12)),
+            (&[4, 4, 5, 6, 6, 5, 2, 5], Some(13)),
+            (&[4, 2, 6, 4, 5, 6, 5, 0], Some(14)),
+            (&[4, 4, 5, 6, 3, 5, 5, 3], Some(15)),
+            (&[4, 5, 3, 5, 4, 5, 6, 2], Some(16)),
+        ];
+        for &(layers_lens, expects) in tests.iter() {
+            // the intervals are up to 10, all the delays repeat after 120
+            assert_eq!(brute_force_delay(layers_lens, 120), expects, "layers {:?}", layers_lens);
+            assert_eq!(find_min_delay(layers_lens), Ok(expects), "layers {:?}", layers_lens);
+            assert_eq!(find_min_delay_with_limits(layers_lens, 4, SEARCH_LIMIT), Ok(expects), "layers {:?}", layers_lens);
+        }
+    }
+
+    #[test]
+    fn puzzle_input() {
+        let layers_lens = parse_layers_lens("input.txt");
+        assert_eq!(find_min_delay(&layers_lens), Ok(Some(3_903_378)));
+        assert_eq!(find_min_delay_with_limits(&layers_lens, 1000, SEARCH_LIMIT), Ok(Some(3_903_378)));
+    }
+}
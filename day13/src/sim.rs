@@ -0,0 +1,139 @@
+use std::fmt::Write;
+
+/// Scanner moving down and up a layer, one position per picosecond
+struct Scanner {
+    range: usize,
+    pos: usize,
+    down: bool,
+}
+
+impl Scanner {
+    fn step(&mut self) {
+        if self.range <= 1 {
+            return;
+        }
+        if self.down && self.pos + 1 == self.range {
+            self.down = false;
+        } else if !self.down && self.pos == 0 {
+            self.down = true;
+        }
+        if self.down { self.pos += 1 } else { self.pos -= 1 }
+    }
+}
+
+/// Step by step simulation of the packet crossing the firewall after waiting
+/// delay picoseconds, returning the layers where it's caught. If frames is
+/// given, the state before and after the scanners move in every picosecond
+/// of the crossing is drawn in it, like in the puzzle text.
+pub fn simulate(layers_lens: &[usize], delay: u64, mut frames: Option<&mut String>) -> Vec<usize> {
+    let mut scanners: Vec<Option<Scanner>> = layers_lens.iter()
+        .map(|&range| if range > 0 { Some(Scanner { range, pos: 0, down: true }) } else { None })
+        .collect();
+
+    // while the packet waits the scanners only go round, skip the full rounds
+    for scanner in scanners.iter_mut().flatten() {
+        let interval = 2 * (scanner.range as u64).saturating_sub(1).max(1);
+        for _ in 0..delay % interval {
+            scanner.step();
+        }
+    }
+
+    let mut caught = Vec::new();
+    for packet in 0..layers_lens.len() {
+        if let Some(Scanner { pos: 0, .. }) = scanners[packet] {
+            caught.push(packet);
+        }
+        if let Some(frames) = frames.as_deref_mut() {
+            writeln!(frames, "Picosecond {}:", delay + packet as u64).unwrap();
+            draw(frames, &scanners, packet);
+            frames.push('\n');
+        }
+
+        for scanner in scanners.iter_mut().flatten() {
+            scanner.step();
+        }
+        if let Some(frames) = frames.as_deref_mut() {
+            draw(frames, &scanners, packet);
+            frames.push('\n');
+        }
+    }
+
+    caught
+}
+
+fn draw(frame: &mut String, scanners: &[Option<Scanner>], packet: usize) {
+    let header: String = (0..scanners.len()).map(|layer| format!(" {:<3}", layer)).collect();
+    writeln!(frame, "{}", header.trim_end()).unwrap();
+
+    let depth = scanners.iter().flatten().map(|scanner| scanner.range).max().unwrap_or(1);
+    for row in 0..depth {
+        let cells: Vec<String> = scanners.iter().enumerate()
+            .map(|(layer, scanner)| {
+                let (open, close) = if row == 0 && layer == packet { ('(', ')') } else { ('[', ']') };
+                match scanner {
+                    None if row == 0 => String::from(if layer == packet { "(.)" } else { "..." }),
+                    Some(scanner) if row < scanner.range =>
+                        format!("{}{}{}", open, if scanner.pos == row { 'S' } else { ' ' }, close),
+                    _ => String::from("   "),
+                }
+            })
+            .collect();
+        writeln!(frame, "{}", cells.join(" ").trim_end()).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::{catching_layers, parse_layers_lens};
+
+    const EXAMPLE: [usize; 7] = [3, 2, 0, 0, 4, 0, 4];
+
+    #[test]
+    fn example_frames() {
+        let mut frames = String::new();
+        assert_eq!(simulate(&EXAMPLE, 0, Some(&mut frames)), vec![0, 6]);
+        assert!(frames.starts_with(concat!(
+            "Picosecond 0:\n",
+            " 0   1   2   3   4   5   6\n",
+            "(S) [S] ... ... [S] ... [S]\n",
+            "[ ] [ ]         [ ]     [ ]\n",
+            "[ ]             [ ]     [ ]\n",
+            "                [ ]     [ ]\n",
+            "\n",
+            " 0   1   2   3   4   5   6\n",
+            "( ) [ ] ... ... [ ] ... [ ]\n",
+            "[S] [S]         [S]     [S]\n",
+            "[ ]             [ ]     [ ]\n",
+            "                [ ]     [ ]\n",
+            "\n",
+            "Picosecond 1:\n",
+            " 0   1   2   3   4   5   6\n",
+            "[ ] ( ) ... ... [ ] ... [ ]\n",
+            "[S] [S]         [S]     [S]\n")));
+        assert!(frames.contains(concat!(
+            "Picosecond 3:\n",
+            " 0   1   2   3   4   5   6\n",
+            "[ ] [ ] ... (.) [ ] ... [ ]\n",
+            "[S] [S]         [ ]     [ ]\n")));
+        assert_eq!(frames.matches("Picosecond").count(), 7);
+
+        let mut frames = String::new();
+        assert_eq!(simulate(&EXAMPLE, 10, Some(&mut frames)), vec![]);
+        assert!(frames.starts_with("Picosecond 10:\n 0   1   2   3   4   5   6\n( ) [S] ... ... [ ] ... [ ]\n"));
+    }
+
+    #[test]
+    fn same_as_arithmetic() {
+        for delay in 0..50 {
+            assert_eq!(simulate(&EXAMPLE, delay, None), catching_layers(&EXAMPLE, delay));
+            assert_eq!(simulate(&[1, 0, 5], delay, None), catching_layers(&[1, 0, 5], delay));
+        }
+
+        let layers_lens = parse_layers_lens("input.txt");
+        for &delay in &[0, 1, 2, 100, 12_345, 3_903_377, 3_903_378] {
+            assert_eq!(simulate(&layers_lens, delay, None), catching_layers(&layers_lens, delay));
+        }
+        assert!(simulate(&layers_lens, 3_903_378, None).is_empty());
+    }
+}
@@ -1,15 +1,12 @@
-use day10;
+use day10::knot_hash_many;
 
 const INPUT: &str = "xlqgujun";
 
 fn main() {
-    let mut grid = Vec::with_capacity(128);
-
-    for row in 0..128 {
-        let hash = calc_hash_from_input(&format!("{}-{}", INPUT, row));
-        let hash = ascii_hash_to_u128(&hash).unwrap();
-        grid.push(hash);
-    }
+    let hash_inputs: Vec<String> = (0..128).map(|row| format!("{}-{}", INPUT, row)).collect();
+    let grid: Vec<u128> = knot_hash_many(&hash_inputs).into_iter()
+        .map(u128::from_be_bytes)
+        .collect();
 
     let used = count_used_squares(&grid);
     println!("Part 1: used={}", used);
@@ -18,17 +15,6 @@ fn main() {
     println!("Part 2: regions={}", regions);
 }
 
-fn calc_hash_from_input(hash_input: &str) -> String {
-    let nums_list = (0..=255).collect();
-    let lens = day10::input_to_ascii(hash_input);
-    day10::apply_dense_knot_tie_hash(nums_list, &lens)
-}
-
-fn ascii_hash_to_u128(hash: &str) -> Result<u128,std::num::ParseIntError>{
-    assert_eq!(hash.len(), 32);
-    u128::from_str_radix(hash, 16)
-}
-
 fn count_used_squares(grid: &[u128]) -> usize {
     let mut count = 0;
     